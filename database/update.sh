@@ -0,0 +1,9 @@
+#!/bin/sh
+# Replaces the embedded copy of the community CHIP-8 database with the latest one, rebuild afterwards.
+set -e
+
+cd "$(dirname "$0")"
+
+for file in programs.json sha1-hashes.json; do
+    curl -fsSL -o "$file" "https://raw.githubusercontent.com/chip-8/chip-8-database/master/database/$file"
+done
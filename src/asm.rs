@@ -0,0 +1,597 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ROMs are loaded at 0x200, so that's where the first byte of output ends up.
+const START: i64 = 0x0200;
+
+// Deep enough for any sensible program, shallow enough to catch a file including itself.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// Deepest a chain of constants defined in terms of each other can go, past this one is assumed to refer to itself.
+const MAX_CONSTANT_DEPTH: usize = 64;
+
+// Where in the source something went wrong, and what.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// A line of source after includes have been expanded and comments removed.
+struct Line {
+    file: String,
+    number: usize,
+    text: String,
+}
+
+impl Line {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError { file: self.file.clone(), line: self.number, message: message.into() }
+    }
+}
+
+// An instruction or data directive, with the line it came from for errors.
+struct Statement<'a> {
+    line: &'a Line,
+    mnemonic: String,
+    operands: Vec<String>,
+}
+
+// Assembles source text into a ROM. Includes are looked up from the current directory.
+//
+// The syntax is the one `disasm` prints: Cowgod's mnemonics, `name:` labels, `NAME EQU value` constants,
+// `DB`/`DW` data, `include "file"` and `;` comments. Numbers can be decimal, `#FF`, `$FF`, `0xFF` or `0b1010`.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut lines: Vec<Line> = Vec::new();
+    expand(source, "<source>", Path::new("."), 0, &mut lines)?;
+
+    Assembler::default().run(&lines)
+}
+
+// Assembles a source file, includes are looked up relative to the file including them.
+pub fn assemble_file(path: &Path) -> Result<Vec<u8>, AsmError> {
+    let name: String = path.display().to_string();
+    let source: String = fs::read_to_string(path)
+        .map_err(|error| AsmError { file: name.clone(), line: 0, message: error.to_string() })?;
+
+    let mut lines: Vec<Line> = Vec::new();
+    expand(&source, &name, path.parent().unwrap_or(Path::new(".")), 0, &mut lines)?;
+
+    Assembler::default().run(&lines)
+}
+
+// Splits source into lines, strips comments and pulls in included files.
+fn expand(source: &str, file: &str, dir: &Path, depth: usize, lines: &mut Vec<Line>) -> Result<(), AsmError> {
+    for (index, raw) in source.lines().enumerate() {
+        let line: Line = Line { file: file.to_string(), number: index + 1, text: strip_comment(raw).trim().to_string() };
+
+        let (first, rest) = split_first_word(&line.text);
+        if !first.eq_ignore_ascii_case("include") {
+            lines.push(line);
+            continue;
+        }
+
+        if depth >= MAX_INCLUDE_DEPTH {
+            return Err(line.error("includes are nested too deeply, does a file include itself?"));
+        }
+
+        let name: &str = rest.trim().trim_matches('"');
+        let path: PathBuf = dir.join(name);
+        let included: String = fs::read_to_string(&path)
+            .map_err(|error| line.error(format!("can't include {}: {error}", path.display())))?;
+
+        expand(&included, &path.display().to_string(), path.parent().unwrap_or(dir), depth + 1, lines)?;
+    }
+
+    Ok(())
+}
+
+// Removes everything after a ; that isn't inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string: bool = false;
+
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+fn split_first_word(text: &str) -> (&str, &str) {
+    match text.find(char::is_whitespace) {
+        Some(index) => (&text[..index], text[index..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+// Splits operands on commas that aren't inside a string.
+fn split_operands(text: &str) -> Vec<String> {
+    if text.trim().is_empty() { return Vec::new() }
+
+    let mut operands: Vec<String> = Vec::new();
+    let mut current: String = String::new();
+    let mut in_string: bool = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => {
+                in_string = !in_string;
+                current.push(c);
+            }
+            ',' if !in_string => operands.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    operands.push(current.trim().to_string());
+
+    operands
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+// V0 to VF.
+fn register(operand: &str) -> Option<u16> {
+    let digit: &str = operand.strip_prefix('V').or_else(|| operand.strip_prefix('v'))?;
+    if digit.len() != 1 { return None }
+
+    u16::from_str_radix(digit, 16).ok()
+}
+
+// Parses a number literal.
+fn number(text: &str) -> Option<i64> {
+    let lower: String = text.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix('#').or_else(|| lower.strip_prefix('$')).or_else(|| lower.strip_prefix("0x")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b").or_else(|| lower.strip_prefix('%')) {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+#[derive(Default)]
+struct Assembler {
+    labels: HashMap<String, i64>,
+    constants: HashMap<String, String>,
+}
+
+impl Assembler {
+    fn run(mut self, lines: &[Line]) -> Result<Vec<u8>, AsmError> {
+        let statements: Vec<Statement> = self.first_pass(lines)?;
+
+        let mut output: Vec<u8> = Vec::new();
+        for statement in &statements {
+            let bytes: Vec<u8> = self
+                .encode(statement)
+                .map_err(|message| statement.line.error(message))?;
+            output.extend(bytes);
+        }
+
+        Ok(output)
+    }
+
+    // Collects labels and constants, and works out the address of every statement.
+    fn first_pass<'a>(&mut self, lines: &'a [Line]) -> Result<Vec<Statement<'a>>, AsmError> {
+        let mut statements: Vec<Statement> = Vec::new();
+        let mut address: i64 = START;
+
+        for line in lines {
+            let mut text: &str = &line.text;
+
+            // Any number of labels can come before the statement.
+            while let Some(colon) = text.find(':') {
+                let name: &str = text[..colon].trim();
+                if !is_identifier(name) { break }
+
+                self.define_label(name, address).map_err(|message| line.error(message))?;
+                text = text[colon + 1..].trim_start();
+            }
+
+            if text.is_empty() { continue }
+
+            let (first, rest) = split_first_word(text);
+            let (second, value) = split_first_word(rest);
+
+            if second.eq_ignore_ascii_case("equ") {
+                if !is_identifier(first) || register(first).is_some() {
+                    return Err(line.error(format!("'{first}' can't be used as a constant name")));
+                }
+                if self.labels.contains_key(first) || self.constants.contains_key(first) {
+                    return Err(line.error(format!("'{first}' is already defined")));
+                }
+                if value.is_empty() {
+                    return Err(line.error(format!("constant '{first}' has no value")));
+                }
+
+                self.constants.insert(first.to_string(), value.to_string());
+                continue;
+            }
+
+            let mnemonic: String = first.to_ascii_uppercase();
+            let operands: Vec<String> = split_operands(rest);
+            let size: i64 = statement_size(&mnemonic, &operands).map_err(|message| line.error(message))?;
+
+            statements.push(Statement { line, mnemonic, operands });
+            address += size;
+        }
+
+        Ok(statements)
+    }
+
+    fn define_label(&mut self, name: &str, address: i64) -> Result<(), String> {
+        if register(name).is_some() {
+            return Err(format!("'{name}' is a register and can't be used as a label"));
+        }
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(format!("'{name}' is already defined"));
+        }
+
+        self.labels.insert(name.to_string(), address);
+        Ok(())
+    }
+
+    // Evaluates a number, label, constant, or a sum of them such as `sprites + 5`.
+    fn eval(&self, expression: &str, depth: usize) -> Result<i64, String> {
+        if depth > MAX_CONSTANT_DEPTH {
+            return Err(format!("constant '{expression}' refers to itself, or goes more than {MAX_CONSTANT_DEPTH} constants deep"));
+        }
+        if expression.trim().is_empty() {
+            return Err("expected a value".to_string());
+        }
+
+        let mut total: i64 = 0;
+        let mut sign: i64 = 1;
+        let mut term: String = String::new();
+
+        for c in expression.chars() {
+            if (c == '+' || c == '-') && !term.trim().is_empty() {
+                total += sign * self.term(term.trim(), depth)?;
+                term.clear();
+                sign = if c == '-' { -1 } else { 1 };
+            } else if c == '-' {
+                sign = -sign;
+            } else if c != '+' {
+                term.push(c);
+            }
+        }
+
+        // Every operator needs something after it.
+        if term.trim().is_empty() {
+            return Err(format!("'{}' ends in an operator", expression.trim()));
+        }
+
+        Ok(total + sign * self.term(term.trim(), depth)?)
+    }
+
+    fn term(&self, term: &str, depth: usize) -> Result<i64, String> {
+        if let Some(value) = number(term) {
+            return Ok(value);
+        }
+        if let Some(&address) = self.labels.get(term) {
+            return Ok(address);
+        }
+        if let Some(expression) = self.constants.get(term) {
+            return self.eval(expression, depth + 1);
+        }
+
+        Err(format!("'{term}' isn't a number, label or constant"))
+    }
+
+    // Evaluates an operand and checks it fits in the given number of bits.
+    // Negative values are allowed for bytes, so `ADD V0, -1` works.
+    fn value(&self, operand: &str, bits: u32) -> Result<u16, String> {
+        let value: i64 = self.eval(operand, 0)?;
+        let max: i64 = (1 << bits) - 1;
+
+        if value > max || value < -(1 << (bits - 1)) || (bits != 8 && value < 0) {
+            return Err(format!("'{operand}' is {value}, which doesn't fit in {bits} bits"));
+        }
+
+        Ok((value & max) as u16)
+    }
+
+    fn encode(&self, statement: &Statement) -> Result<Vec<u8>, String> {
+        let operands: Vec<&str> = statement.operands.iter().map(|operand| operand.as_str()).collect();
+
+        match statement.mnemonic.as_str() {
+            "DB" => {
+                let mut bytes: Vec<u8> = Vec::new();
+                for operand in operands {
+                    match operand.strip_prefix('"').and_then(|text| text.strip_suffix('"')) {
+                        Some(text) => bytes.extend(text.bytes()),
+                        None => bytes.push(self.value(operand, 8)? as u8),
+                    }
+                }
+                Ok(bytes)
+            }
+            "DW" => {
+                let mut bytes: Vec<u8> = Vec::new();
+                for operand in operands {
+                    bytes.extend(self.value(operand, 16)?.to_be_bytes());
+                }
+                Ok(bytes)
+            }
+            _ => {
+                let words: Vec<u16> = self.encode_instruction(&statement.mnemonic, &operands)?;
+                Ok(words.iter().flat_map(|word| word.to_be_bytes()).collect())
+            }
+        }
+    }
+
+    // Encodes an instruction, using the same mnemonics and operands as Instruction's Display.
+    fn encode_instruction(&self, mnemonic: &str, operands: &[&str]) -> Result<Vec<u16>, String> {
+        let upper: Vec<String> = operands.iter().map(|operand| operand.to_ascii_uppercase()).collect();
+        let upper: Vec<&str> = upper.iter().map(|operand| operand.as_str()).collect();
+        let registers: Vec<Option<u16>> = operands.iter().map(|operand| register(operand)).collect();
+
+        let opcode: u16 = match (mnemonic, upper.as_slice(), registers.as_slice()) {
+            ("CLS", [], _) => 0x00E0,
+            ("RET", [], _) => 0x00EE,
+            ("SCD", [_], _) => 0x00C0 | self.value(operands[0], 4)?,
+            ("SCU", [_], _) => 0x00D0 | self.value(operands[0], 4)?,
+            ("SCR", [], _) => 0x00FB,
+            ("SCL", [], _) => 0x00FC,
+            ("EXIT", [], _) => 0x00FD,
+            ("LOW", [], _) => 0x00FE,
+            ("HIGH", [], _) => 0x00FF,
+            ("JP", ["V0", _], _) => 0xB000 | self.value(operands[1], 12)?,
+            ("JP", [_], _) => 0x1000 | self.value(operands[0], 12)?,
+            ("CALL", [_], _) => 0x2000 | self.value(operands[0], 12)?,
+            ("SE", [_, _], [Some(x), Some(y)]) => 0x5000 | x << 8 | y << 4,
+            ("SE", [_, _], [Some(x), None]) => 0x3000 | x << 8 | self.value(operands[1], 8)?,
+            ("SNE", [_, _], [Some(x), Some(y)]) => 0x9000 | x << 8 | y << 4,
+            ("SNE", [_, _], [Some(x), None]) => 0x4000 | x << 8 | self.value(operands[1], 8)?,
+            ("SAVE", [_, _], [Some(x), Some(y)]) => 0x5002 | x << 8 | y << 4,
+            ("LOAD", [_, _], [Some(x), Some(y)]) => 0x5003 | x << 8 | y << 4,
+            ("LD", ["I", long], _) if long.starts_with("LONG ") => {
+                let nnnn: u16 = self.value(operands[1][5..].trim(), 16)?;
+                return Ok(vec![0xF000, nnnn]);
+            }
+            ("LD", ["I", _], _) => 0xA000 | self.value(operands[1], 12)?,
+            ("LD", [_, "DT"], [Some(x), _]) => 0xF007 | x << 8,
+            ("LD", [_, "K"], [Some(x), _]) => 0xF00A | x << 8,
+            ("LD", ["DT", _], [_, Some(x)]) => 0xF015 | x << 8,
+            ("LD", ["ST", _], [_, Some(x)]) => 0xF018 | x << 8,
+            ("LD", ["F", _], [_, Some(x)]) => 0xF029 | x << 8,
+            ("LD", ["HF", _], [_, Some(x)]) => 0xF030 | x << 8,
+            ("LD", ["B", _], [_, Some(x)]) => 0xF033 | x << 8,
+            ("LD", ["[I]", _], [_, Some(x)]) => 0xF055 | x << 8,
+            ("LD", [_, "[I]"], [Some(x), _]) => 0xF065 | x << 8,
+            ("LD", ["R", _], [_, Some(x)]) => 0xF075 | x << 8,
+            ("LD", [_, "R"], [Some(x), _]) => 0xF085 | x << 8,
+            ("LD", [_, _], [Some(x), Some(y)]) => 0x8000 | x << 8 | y << 4,
+            ("LD", [_, _], [Some(x), None]) => 0x6000 | x << 8 | self.value(operands[1], 8)?,
+            ("ADD", ["I", _], [_, Some(x)]) => 0xF01E | x << 8,
+            ("ADD", [_, _], [Some(x), Some(y)]) => 0x8004 | x << 8 | y << 4,
+            ("ADD", [_, _], [Some(x), None]) => 0x7000 | x << 8 | self.value(operands[1], 8)?,
+            ("OR", [_, _], [Some(x), Some(y)]) => 0x8001 | x << 8 | y << 4,
+            ("AND", [_, _], [Some(x), Some(y)]) => 0x8002 | x << 8 | y << 4,
+            ("XOR", [_, _], [Some(x), Some(y)]) => 0x8003 | x << 8 | y << 4,
+            ("SUB", [_, _], [Some(x), Some(y)]) => 0x8005 | x << 8 | y << 4,
+            ("SUBN", [_, _], [Some(x), Some(y)]) => 0x8007 | x << 8 | y << 4,
+            // Cowgod writes these as SHR Vx {, Vy}, without Vy the register is shifted in place.
+            ("SHR", [_, _], [Some(x), Some(y)]) => 0x8006 | x << 8 | y << 4,
+            ("SHR", [_], [Some(x)]) => 0x8006 | x << 8 | x << 4,
+            ("SHL", [_, _], [Some(x), Some(y)]) => 0x800E | x << 8 | y << 4,
+            ("SHL", [_], [Some(x)]) => 0x800E | x << 8 | x << 4,
+            ("RND", [_, _], [Some(x), None]) => 0xC000 | x << 8 | self.value(operands[1], 8)?,
+            ("DRW", [_, _, _], [Some(x), Some(y), None]) => 0xD000 | x << 8 | y << 4 | self.value(operands[2], 4)?,
+            ("SKP", [_], [Some(x)]) => 0xE09E | x << 8,
+            ("SKNP", [_], [Some(x)]) => 0xE0A1 | x << 8,
+            ("PLANE", [_], [None]) => 0xF001 | self.value(operands[0], 4)? << 8,
+            ("AUDIO", [], _) => 0xF002,
+            ("PITCH", [_], [Some(x)]) => 0xF03A | x << 8,
+            _ => return Err(format!("can't assemble '{mnemonic} {}'", operands.join(", "))),
+        };
+
+        Ok(vec![opcode])
+    }
+}
+
+// How many bytes a statement takes up, needed to place labels before anything is encoded.
+fn statement_size(mnemonic: &str, operands: &[String]) -> Result<i64, String> {
+    match mnemonic {
+        "DB" => {
+            let mut size: i64 = 0;
+            for operand in operands {
+                size += match operand.strip_prefix('"').and_then(|text| text.strip_suffix('"')) {
+                    Some(text) => text.len() as i64,
+                    None => 1,
+                };
+            }
+            Ok(size)
+        }
+        "DW" => Ok(2 * operands.len() as i64),
+        "LD" if operands.len() == 2 && operands[1].to_ascii_uppercase().starts_with("LONG ") => Ok(4),
+        _ if is_identifier(mnemonic) => Ok(2),
+        _ => Err(format!("'{mnemonic}' isn't an instruction")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble;
+    use crate::instruction::{decode, Instruction};
+    use crate::platform::Platform;
+
+    fn assemble_words(source: &str) -> Vec<u16> {
+        let rom: Vec<u8> = assemble(source).unwrap_or_else(|error| panic!("'{source}' didn't assemble: {error}"));
+        rom.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect()
+    }
+
+    #[test]
+    fn every_mnemonic() {
+        let cases: &[(&str, &[u16])] = &[
+            ("CLS", &[0x00E0]),
+            ("RET", &[0x00EE]),
+            ("SCD 5", &[0x00C5]),
+            ("SCU 3", &[0x00D3]),
+            ("SCR", &[0x00FB]),
+            ("SCL", &[0x00FC]),
+            ("EXIT", &[0x00FD]),
+            ("LOW", &[0x00FE]),
+            ("HIGH", &[0x00FF]),
+            ("JP #234", &[0x1234]),
+            ("CALL #456", &[0x2456]),
+            ("SE V1, #22", &[0x3122]),
+            ("SNE V2, #33", &[0x4233]),
+            ("SE V3, V4", &[0x5340]),
+            ("SAVE V1, V5", &[0x5152]),
+            ("LOAD V1, V5", &[0x5153]),
+            ("LD V5, #66", &[0x6566]),
+            ("ADD V6, #77", &[0x7677]),
+            ("ADD V6, -1", &[0x76FF]),
+            ("LD V7, V8", &[0x8780]),
+            ("OR V7, V8", &[0x8781]),
+            ("AND V7, V8", &[0x8782]),
+            ("XOR V7, V8", &[0x8783]),
+            ("ADD V7, V8", &[0x8784]),
+            ("SUB V7, V8", &[0x8785]),
+            ("SHR V7, V8", &[0x8786]),
+            ("SHR V7", &[0x8776]),
+            ("SUBN V7, V8", &[0x8787]),
+            ("SHL V7, V8", &[0x878E]),
+            ("SHL V7", &[0x877E]),
+            ("SNE V9, VA", &[0x99A0]),
+            ("LD I, #ABC", &[0xAABC]),
+            ("JP V0, #BCD", &[0xBBCD]),
+            ("RND VC, #DD", &[0xCCDD]),
+            ("DRW VD, VE, #F", &[0xDDEF]),
+            ("SKP VE", &[0xEE9E]),
+            ("SKNP VE", &[0xEEA1]),
+            ("LD I, LONG #1234", &[0xF000, 0x1234]),
+            ("PLANE 3", &[0xF301]),
+            ("AUDIO", &[0xF002]),
+            ("LD VF, DT", &[0xFF07]),
+            ("LD VF, K", &[0xFF0A]),
+            ("LD DT, VF", &[0xFF15]),
+            ("LD ST, VF", &[0xFF18]),
+            ("ADD I, VF", &[0xFF1E]),
+            ("LD F, VF", &[0xFF29]),
+            ("LD HF, VF", &[0xFF30]),
+            ("LD B, VF", &[0xFF33]),
+            ("PITCH VF", &[0xFF3A]),
+            ("LD [I], VF", &[0xFF55]),
+            ("LD VF, [I]", &[0xFF65]),
+            ("LD R, VF", &[0xFF75]),
+            ("LD VF, R", &[0xFF85]),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(assemble_words(source), *expected, "{source}");
+        }
+    }
+
+    // Whatever decode understands, Display prints in a form the assembler turns back into the same opcode.
+    #[test]
+    fn every_decoded_opcode_assembles_back() {
+        for opcode in 0..=u16::MAX {
+            let instruction: Instruction = decode(opcode);
+            if matches!(instruction, Instruction::Unknown { .. } | Instruction::LoadLongI) { continue }
+
+            assert_eq!(assemble_words(&instruction.to_string()), [opcode], "{instruction}");
+        }
+    }
+
+    #[test]
+    fn forward_references() {
+        let source: &str = "
+            SPEED EQU STEP + 1
+            STEP EQU 2
+            start:
+                CALL draw
+                JP start
+            draw:
+                LD I, sprite
+                ADD V0, SPEED
+                RET
+            sprite: DB #F0, %10010000, 144, \"ab\"
+                DW sprite + 1
+        ";
+
+        assert_eq!(
+            assemble(source).unwrap(),
+            [0x22, 0x04, 0x12, 0x00, 0xA2, 0x0A, 0x70, 0x03, 0x00, 0xEE, 0xF0, 0x90, 0x90, b'a', b'b', 0x02, 0x0B],
+        );
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        let cases: &[(&str, usize, &str)] = &[
+            ("CLS\nLD V0, 256", 2, "doesn't fit in 8 bits"),
+            ("ADD V1, -129", 1, "doesn't fit in 8 bits"),
+            ("DRW V0, V1, 16", 1, "doesn't fit in 4 bits"),
+            ("JP #1000", 1, "doesn't fit in 12 bits"),
+            ("LD I, -1", 1, "doesn't fit in 12 bits"),
+            ("PLANE 16", 1, "doesn't fit in 4 bits"),
+            ("CLS\n\nJP nowhere", 3, "'nowhere' isn't a number, label or constant"),
+            ("a:\na:", 2, "'a' is already defined"),
+            ("V1: CLS", 1, "'V1' is a register"),
+            ("LOOP EQU LOOP\nJP LOOP", 2, "refers to itself"),
+            ("CLS\nSPEED EQU\nLD V0, SPEED", 2, "'SPEED' has no value"),
+            ("LD V0, 1 +", 1, "'1 +' ends in an operator"),
+            ("LD V0, 1 + -", 1, "ends in an operator"),
+            ("STEP EQU 2 -\nLD V0, STEP", 2, "'2 -' ends in an operator"),
+            ("MOV V0, V1", 1, "can't assemble"),
+            ("DRW V0, V1", 1, "can't assemble"),
+        ];
+
+        for (source, line, message) in cases {
+            let error: AsmError = assemble(source).expect_err(source);
+            assert_eq!(error.line, *line, "{source}");
+            assert!(error.message.contains(message), "{source}: {}", error.message);
+        }
+    }
+
+    #[test]
+    fn disassembly_assembles_back() {
+        // Calls, skips, jumps to odd addresses, data reached through I and a trailing byte nothing points at.
+        let rom: &[u8] = &[
+            0x22, 0x08, // CALL sub_208
+            0x3F, 0x01, // SE VF, #01
+            0x12, 0x00, // JP label_200
+            0x12, 0x13, // JP label_213
+            0xA2, 0x0F, // LD I, data_20F
+            0xD0, 0x15, // DRW V0, V1, #5
+            0x70, 0x01, // ADD V0, #01
+            0x00, 0xEE, // RET
+            0xF0, 0x90, 0x90, // data_20F, running into the next label
+            0x00, 0xFD, // EXIT
+            0x42, // unreachable
+        ];
+
+        for platform in [Platform::Vip, Platform::XoChip] {
+            assert_eq!(assemble(&disassemble(rom, platform)).unwrap(), rom);
+        }
+
+        // XO-CHIP's four byte LD I, LONG, skipped over as a whole.
+        let xo: &[u8] = &[0x30, 0x00, 0xF0, 0x00, 0x02, 0x08, 0x12, 0x00, 0xAB];
+        assert_eq!(assemble(&disassemble(xo, Platform::XoChip)).unwrap(), xo);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::system::Emulator;
+
+use std::f32::consts::TAU;
+use std::str::FromStr;
+
+// Shape of the tone played while the sound timer is running.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+    Sawtooth,
+}
+
+impl Waveform {
+    // Value at a point through one cycle, phase goes from 0 to 1 and the result from -1 to 1.
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+// Parses a waveform name, used for the --waveform option.
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "square" => Ok(Waveform::Square),
+            "sine" => Ok(Waveform::Sine),
+            "triangle" => Ok(Waveform::Triangle),
+            "sawtooth" | "saw" => Ok(Waveform::Sawtooth),
+            _ => Err(format!("unknown waveform '{s}', expected square, sine, triangle or sawtooth")),
+        }
+    }
+}
+
+// Generates the samples for the buzzer. Doesn't know about any audio library, the frontend asks it to fill buffers.
+pub struct Tone {
+    pub frequency: f32, // Pitch of the tone in Hz.
+    pub volume: f32, // From 0 to 1.
+    pub waveform: Waveform,
+    pub muted: bool,
+    playing: bool, // Whether the sound timer is running.
+    pattern: Option<([u8; 16], f32)>, // XO-CHIP's audio pattern and how many of its bits play each second.
+    sample_rate: f32,
+    phase: f32, // Position through the current cycle, or through the pattern's bits.
+}
+
+impl Tone {
+    pub fn new(sample_rate: u32, frequency: f32, volume: f32, waveform: Waveform) -> Self {
+        Self {
+            frequency,
+            volume,
+            waveform,
+            muted: false,
+            playing: false,
+            pattern: None,
+            sample_rate: sample_rate as f32,
+            phase: 0.0,
+        }
+    }
+
+    // Called once a frame with whether the emulator is actually running, so a paused game doesn't keep beeping.
+    pub fn update(&mut self, emulator: &Emulator, running: bool) {
+        self.playing = running && emulator.sound != 0;
+
+        // XO-CHIP plays its 128 bit pattern instead, once a ROM has loaded one with 0xF002.
+        self.pattern = if emulator.platform.supports_xochip() && emulator.audio_pattern != [0; 16] {
+            let rate: f32 = 4000.0 * 2f32.powf((emulator.pitch as f32 - 64.0) / 48.0);
+            Some((emulator.audio_pattern, rate))
+        } else {
+            None
+        };
+    }
+
+    pub fn fill(&mut self, output: &mut [f32]) {
+        if !self.playing || self.muted {
+            output.fill(0.0);
+            return;
+        }
+
+        for sample in output.iter_mut() {
+            match self.pattern {
+                Some((pattern, rate)) => {
+                    let bit: usize = self.phase as usize;
+                    let high: bool = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+
+                    *sample = if high { self.volume } else { -self.volume };
+                    self.phase = (self.phase + rate / self.sample_rate) % 128.0;
+                }
+                None => {
+                    *sample = self.waveform.sample(self.phase) * self.volume;
+                    self.phase = (self.phase + self.frequency / self.sample_rate).fract();
+                }
+            }
+        }
+    }
+}
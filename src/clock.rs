@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+// Rate the delay and sound timers count down at, and the rate frames are emulated at, whatever the speed.
+pub const TIMER_HZ: u32 = 60;
+
+// Most frames caught up on at once after the host falls behind, past this the game slows down instead of
+// rushing through what it missed, e.g. while the window is being dragged.
+const MAX_CATCH_UP: u32 = 6;
+
+// Slowest and fastest speeds, slower than one instruction a frame isn't possible.
+pub const MIN_IPS: u32 = 60;
+pub const MAX_IPS: u32 = 1_000_000;
+
+// Keeps the emulator in step with real time. Real time is turned into 60Hz frames, each of which runs its share
+// of the instructions and ticks the timers once, so the timers run at 60Hz however fast instructions run and
+// however often the host gets round to it.
+pub struct Clock {
+    pub ips: u32, // Instructions per second.
+    pending: u128, // Real time that hasn't been emulated yet, in nanoseconds times TIMER_HZ, so a frame is exactly NANOS.
+    remainder: u32, // Instructions owed but not run yet, in 60ths. Kept as a whole number so none get lost.
+}
+
+// Nanoseconds in a second.
+const NANOS: u128 = 1_000_000_000;
+
+impl Clock {
+    // Starts at a speed, anything outside MIN_IPS to MAX_IPS is moved to the nearest end of that range.
+    pub fn new(ips: u32) -> Self {
+        Self { ips: ips.clamp(MIN_IPS, MAX_IPS), pending: 0, remainder: 0 }
+    }
+
+    // Adds real time that's passed and returns how many frames are now due. Time is counted in whole
+    // nanoseconds, so a second is always 60 frames however it's split up.
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.pending += elapsed.as_nanos() * TIMER_HZ as u128;
+
+        let frames: u128 = self.pending / NANOS;
+        self.pending %= NANOS;
+
+        frames.min(MAX_CATCH_UP as u128) as u32
+    }
+
+    // How long until the next frame is due.
+    pub fn until_next_frame(&self) -> Duration {
+        Duration::from_nanos(((NANOS - self.pending) / TIMER_HZ as u128) as u64)
+    }
+
+    // Instructions to run in the next frame. They're spread evenly, so 700 per second runs 11 or 12 a frame.
+    pub fn instructions_for_frame(&mut self) -> u32 {
+        self.remainder += self.ips;
+
+        let instructions: u32 = self.remainder / TIMER_HZ;
+        self.remainder %= TIMER_HZ;
+
+        instructions
+    }
+
+    // Changes the speed by a factor, staying within the hotkeys' limits.
+    pub fn scale_speed(&mut self, factor: f64) {
+        self.ips = ((self.ips as f64 * factor).round() as u32).clamp(MIN_IPS, MAX_IPS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Adds up the frames from a second of real time handed over in pieces, none long enough to be capped.
+    fn frames_in(pieces: &[u64]) -> u32 {
+        let mut clock: Clock = Clock::new(700);
+        assert_eq!(pieces.iter().sum::<u64>(), 1_000_000_000);
+
+        pieces.iter().map(|&nanos| clock.advance(Duration::from_nanos(nanos))).sum()
+    }
+
+    #[test]
+    fn a_second_is_sixty_frames() {
+        let pieces: [Vec<u64>; 4] = [
+            vec![1_000_000; 1000],
+            [vec![83_333_333; 11], vec![83_333_337]].concat(),
+            [vec![16_666_666; 59], vec![16_666_706]].concat(),
+            [vec![7_000_000; 142], vec![6_000_000]].concat(),
+        ];
+
+        for pieces in pieces {
+            assert_eq!(frames_in(&pieces), 60);
+        }
+    }
+
+    #[test]
+    fn until_next_frame() {
+        let mut clock: Clock = Clock::new(700);
+        assert_eq!(clock.advance(Duration::from_millis(10)), 0);
+        assert_eq!(clock.until_next_frame(), Duration::from_nanos(6_666_666));
+    }
+
+    #[test]
+    fn instructions_spread_without_drift() {
+        let mut clock: Clock = Clock::new(700);
+        let frames: Vec<u32> = (0..60).map(|_| clock.instructions_for_frame()).collect();
+
+        assert_eq!(frames.iter().sum::<u32>(), 700);
+        assert!(frames.iter().all(|&instructions| instructions == 11 || instructions == 12));
+
+        // Ten minutes later it's still exactly on time.
+        let later: u32 = (0..60 * 600).map(|_| clock.instructions_for_frame()).sum();
+        assert_eq!(later, 700 * 600);
+    }
+
+    #[test]
+    fn speed_stays_in_range() {
+        let mut clock: Clock = Clock::new(1000);
+
+        clock.scale_speed(1e9);
+        assert_eq!(clock.ips, MAX_IPS);
+
+        clock.scale_speed(1e-9);
+        assert_eq!(clock.ips, MIN_IPS);
+
+        assert_eq!(Clock::new(1).ips, MIN_IPS);
+        assert_eq!(Clock::new(u32::MAX).ips, MAX_IPS);
+    }
+
+    #[test]
+    fn catching_up_is_capped() {
+        let mut clock: Clock = Clock::new(700);
+
+        assert_eq!(clock.advance(Duration::from_secs(10)), MAX_CATCH_UP);
+
+        // What was skipped is dropped rather than owed.
+        assert_eq!(clock.advance(Duration::from_millis(10)), 0);
+        assert_eq!(clock.advance(Duration::from_millis(100)), MAX_CATCH_UP);
+    }
+}
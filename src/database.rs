@@ -0,0 +1,225 @@
+use crate::error::EmulatorError;
+use crate::palette::{overlay, parse_colour, Palette};
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+// Settings for known ROMs, in the format of the community CHIP-8 database:
+// https://github.com/chip-8/chip-8-database
+// programs.json lists every program and its ROMs, sha1-hashes.json maps each ROM's SHA-1 to its program.
+// The copies in database/ are built into the binary, --database can point at a newer checkout instead.
+const EMBEDDED_PROGRAMS: &str = include_str!("../database/programs.json");
+const EMBEDDED_HASHES: &str = include_str!("../database/sha1-hashes.json");
+
+#[derive(Debug, Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    roms: HashMap<String, RomEntry>, // By SHA-1.
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RomEntry {
+    #[serde(default)]
+    platforms: Vec<String>, // Platforms the ROM runs on, best first.
+    #[serde(default)]
+    quirky_platforms: HashMap<String, BTreeMap<String, bool>>, // Quirks that differ from the platform's.
+    tickrate: Option<u32>, // Instructions per frame.
+    #[serde(default)]
+    keys: BTreeMap<String, u8>, // Actions like "up" and "a" to CHIP-8 keys.
+    colors: Option<Colours>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Colours {
+    #[serde(default)]
+    pixels: Vec<String>, // "#RRGGBB", background first.
+}
+
+// Everything the database knows about how to run a ROM. Anything it doesn't know is left as None.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RomSettings {
+    pub title: String,
+    pub platform: Option<Platform>,
+    pub quirks: Option<Quirks>,
+    pub instructions_per_frame: Option<u32>,
+    pub buttons: BTreeMap<String, u8>, // Controller button names to CHIP-8 keys.
+    pub colours: Vec<[u8; 3]>, // Palette entries from the background up.
+}
+
+impl RomSettings {
+    // The palette with the database's colours in place of the first few.
+    pub fn palette(&self, palette: Palette) -> Palette {
+        overlay(palette, &self.colours)
+    }
+}
+
+pub struct Database {
+    programs: Vec<Program>,
+    hashes: HashMap<String, usize>, // SHA-1 to index in programs.
+}
+
+impl Database {
+    pub fn embedded() -> Result<Database, EmulatorError> {
+        Database::from_json(EMBEDDED_PROGRAMS, EMBEDDED_HASHES)
+    }
+
+    // Loads programs.json and sha1-hashes.json from a directory, e.g. the database folder of a checkout.
+    pub fn load(dir: &Path) -> Result<Database, EmulatorError> {
+        let programs: String = fs::read_to_string(dir.join("programs.json"))?;
+        let hashes: String = fs::read_to_string(dir.join("sha1-hashes.json"))?;
+
+        Database::from_json(&programs, &hashes)
+    }
+
+    pub fn from_json(programs: &str, hashes: &str) -> Result<Database, EmulatorError> {
+        let bad_database = |error: serde_json::Error| EmulatorError::BadConfig { reason: format!("ROM database: {error}") };
+
+        Ok(Database {
+            programs: serde_json::from_str(programs).map_err(bad_database)?,
+            hashes: serde_json::from_str(hashes).map_err(bad_database)?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    // Looks a ROM up by its contents.
+    pub fn lookup(&self, rom: &[u8]) -> Option<RomSettings> {
+        let hash: String = sha1(rom);
+        let program: &Program = self.programs.get(*self.hashes.get(&hash)?)?;
+        let entry: &RomEntry = program.roms.get(&hash)?;
+
+        // The first platform listed that this interpreter has.
+        let (platform_id, platform) = entry
+            .platforms
+            .iter()
+            .find_map(|id| platform(id).map(|platform| (id, platform)))
+            .unzip();
+
+        let quirks: Option<Quirks> = platform_id.map(|id| {
+            let mut quirks: Quirks = base_quirks(id, platform.unwrap());
+            for (name, &value) in entry.quirky_platforms.get(id).into_iter().flatten() {
+                apply_quirk(&mut quirks, name, value);
+            }
+            quirks
+        });
+
+        // Actions are named after a controller, so they map straight onto its buttons.
+        let buttons: BTreeMap<String, u8> = entry
+            .keys
+            .iter()
+            .filter_map(|(action, &key)| button(action).map(|button| (button.to_string(), key)))
+            .filter(|&(_, key)| key < 16)
+            .collect();
+
+        let colours: Vec<[u8; 3]> = entry
+            .colors
+            .iter()
+            .flat_map(|colours| &colours.pixels)
+            .map_while(|colour| parse_colour(colour))
+            .collect();
+
+        Some(RomSettings {
+            title: program.title.clone(),
+            platform,
+            quirks,
+            instructions_per_frame: entry.tickrate,
+            buttons,
+            colours,
+        })
+    }
+}
+
+// Lowercase hex SHA-1, as the database keys ROMs.
+pub fn sha1(rom: &[u8]) -> String {
+    Sha1::digest(rom).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// The database's platform ids, the ones this interpreter can't run are None.
+fn platform(id: &str) -> Option<Platform> {
+    match id {
+        "originalChip8" | "hybridVIP" | "modernChip8" => Some(Platform::Vip),
+        "chip48" => Some(Platform::Chip48),
+        "superchip1" => Some(Platform::Schip10),
+        "superchip" => Some(Platform::Schip11),
+        "xochip" => Some(Platform::XoChip),
+        _ => None,
+    }
+}
+
+// Modern CHIP-8 runs on the VIP platform, but without any of its quirks except clipping.
+fn base_quirks(id: &str, platform: Platform) -> Quirks {
+    match id {
+        "modernChip8" => Quirks { vf_reset: false, memory: false, display_wait: false, clipping: true, shifting: false, jumping: false },
+        _ => platform.quirks(),
+    }
+}
+
+// The database's quirk names, some of which mean the opposite of ours.
+fn apply_quirk(quirks: &mut Quirks, name: &str, value: bool) {
+    match name {
+        "shift" => quirks.shifting = value,
+        "memoryLeaveIUnchanged" => quirks.memory = !value,
+        // I += X is a third behaviour the memory quirk can't express, so the platform's is kept.
+        "memoryIncrementByX" => {}
+        "wrap" => quirks.clipping = !value,
+        "jump" => quirks.jumping = value,
+        "vblank" => quirks.display_wait = value,
+        "logic" => quirks.vf_reset = value,
+        _ => {}
+    }
+}
+
+fn button(action: &str) -> Option<&'static str> {
+    match action {
+        "up" => Some("dpup"),
+        "down" => Some("dpdown"),
+        "left" => Some("dpleft"),
+        "right" => Some("dpright"),
+        "a" => Some("a"),
+        "b" => Some("b"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database(rom: &[u8], entry: &str) -> Database {
+        let hash: String = sha1(rom);
+        let programs: String = format!(r#"[{{"title": "Test", "roms": {{"{hash}": {entry}}}}}]"#);
+        let hashes: String = format!(r#"{{"{hash}": 0}}"#);
+
+        Database::from_json(&programs, &hashes).unwrap()
+    }
+
+    #[test]
+    fn memory_quirks() {
+        let rom: &[u8] = &[0x12, 0x00];
+
+        let unchanged: Database = database(rom, r#"{"platforms": ["originalChip8"], "quirkyPlatforms": {"originalChip8": {"memoryLeaveIUnchanged": true}}}"#);
+        assert!(!unchanged.lookup(rom).unwrap().quirks.unwrap().memory);
+
+        // Incrementing by X isn't something the quirks can do, so it doesn't turn the VIP's increment off.
+        let by_x: Database = database(rom, r#"{"platforms": ["originalChip8"], "quirkyPlatforms": {"originalChip8": {"memoryIncrementByX": true}}}"#);
+        assert_eq!(by_x.lookup(rom).unwrap().quirks, Some(Platform::Vip.quirks()));
+    }
+
+    #[test]
+    fn embedded_database_parses() {
+        Database::embedded().unwrap();
+    }
+}
@@ -0,0 +1,335 @@
+use crate::instruction::{decode, Instruction};
+use crate::system::Emulator;
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+// Commands typed into the debugger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Continue, // Run until a breakpoint.
+    Pause, // Stop before the next instruction.
+    Step, // Run one instruction.
+    Next, // Run one instruction, treating a CALL as a single step.
+    Finish, // Run until the current subroutine returns.
+    Break(u16), // Add a breakpoint at an address.
+    Delete(u16), // Remove a breakpoint.
+    Registers, // Print the registers.
+    Help,
+}
+
+// What the debugger is waiting for before it pauses again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Paused,
+    Running,
+    Step,
+    StepOver { sp: u8 }, // Pause once the stack is back to this depth.
+    StepOut { sp: u8 }, // Pause once the stack is shallower than this.
+}
+
+pub const HELP: &str = "\
+c, continue      run until a breakpoint
+p, pause         pause before the next instruction
+s, step          run one instruction
+n, next          run one instruction, stepping over CALLs
+f, finish        run until the current subroutine returns
+b, break <addr>  add a breakpoint, e.g. b 20A
+d, delete <addr> remove a breakpoint
+r, regs          show the registers
+h, help          show this";
+
+// Decides which instructions the frontend is allowed to run.
+pub struct Debugger {
+    pub breakpoints: BTreeSet<u16>,
+    mode: Mode,
+    resumed_at: Option<u16>, // Breakpoint to ignore once, so continuing from it doesn't stop straight away.
+}
+
+impl Debugger {
+    pub fn new(paused: bool) -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            mode: if paused { Mode::Paused } else { Mode::Running },
+            resumed_at: None,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.mode == Mode::Paused
+    }
+
+    // Applies a command, returning anything that should be shown to the user.
+    pub fn command(&mut self, command: Command, emulator: &Emulator) -> Option<String> {
+        match command {
+            Command::Continue => self.resume(Mode::Running, emulator),
+            Command::Pause => {
+                self.mode = Mode::Paused;
+                return Some(registers(emulator));
+            }
+            Command::Step => self.resume(Mode::Step, emulator),
+            Command::Next => {
+                // Only a CALL needs stepping over, anything else is a normal step.
+                let mode: Mode = match next_instruction(emulator) {
+                    Some(Instruction::Call { .. }) => Mode::StepOver { sp: emulator.sp },
+                    _ => Mode::Step,
+                };
+                self.resume(mode, emulator);
+            }
+            Command::Finish => {
+                if emulator.sp == 0 {
+                    return Some("Not in a subroutine".to_string());
+                }
+                self.resume(Mode::StepOut { sp: emulator.sp }, emulator);
+            }
+            Command::Break(addr) => {
+                self.breakpoints.insert(addr);
+                return Some(format!("Breakpoint at {addr:03X}"));
+            }
+            Command::Delete(addr) => {
+                return if self.breakpoints.remove(&addr) {
+                    Some(format!("Removed breakpoint at {addr:03X}"))
+                } else {
+                    Some(format!("No breakpoint at {addr:03X}"))
+                };
+            }
+            Command::Registers => return Some(registers(emulator)),
+            Command::Help => return Some(HELP.to_string()),
+        }
+
+        None
+    }
+
+    fn resume(&mut self, mode: Mode, emulator: &Emulator) {
+        self.mode = mode;
+        self.resumed_at = Some(emulator.pc);
+    }
+
+    // Called before each instruction, returns false if the emulator should stop here.
+    pub fn before_step(&mut self, emulator: &Emulator) -> bool {
+        if self.mode == Mode::Paused { return false }
+
+        let resumed_here: bool = self.resumed_at.take() == Some(emulator.pc);
+
+        if self.breakpoints.contains(&emulator.pc) && !resumed_here {
+            self.mode = Mode::Paused;
+            return false;
+        }
+
+        true
+    }
+
+    // Called after each instruction, returns true if the debugger just paused.
+    pub fn after_step(&mut self, emulator: &Emulator) -> bool {
+        let done: bool = match self.mode {
+            Mode::Step => true,
+            Mode::StepOver { sp } => emulator.sp <= sp,
+            Mode::StepOut { sp } => emulator.sp < sp,
+            Mode::Paused | Mode::Running => false,
+        };
+
+        if done { self.mode = Mode::Paused }
+
+        done
+    }
+}
+
+// Parses a line typed into the debugger.
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let name: &str = words.next().unwrap_or("");
+    let argument: Option<&str> = words.next();
+
+    let address = || -> Result<u16, String> {
+        let text: &str = argument.ok_or("expected an address, e.g. 20A")?;
+        let hex: &str = text.trim_start_matches("0x").trim_start_matches('#');
+
+        u16::from_str_radix(hex, 16).map_err(|_| format!("'{text}' isn't a hex address"))
+    };
+
+    match name {
+        "c" | "continue" => Ok(Command::Continue),
+        "p" | "pause" => Ok(Command::Pause),
+        "s" | "step" => Ok(Command::Step),
+        "n" | "next" => Ok(Command::Next),
+        "f" | "finish" => Ok(Command::Finish),
+        "b" | "break" => Ok(Command::Break(address()?)),
+        "d" | "delete" => Ok(Command::Delete(address()?)),
+        "r" | "regs" => Ok(Command::Registers),
+        "h" | "help" => Ok(Command::Help),
+        _ => Err(format!("unknown command '{name}', type h for help")),
+    }
+}
+
+// The instruction at the program counter, if there is one.
+fn next_instruction(emulator: &Emulator) -> Option<Instruction> {
+    let high: u8 = emulator.read(emulator.pc as usize).ok()?;
+    let low: u8 = emulator.read(emulator.pc as usize + 1).ok()?;
+
+    Some(decode((high as u16) << 8 | low as u16))
+}
+
+// The registers, stack and timers on one line, for a view that's kept up to date while running.
+pub fn status(emulator: &Emulator) -> String {
+    let registers: String = emulator.vx.iter().map(|v| format!("{v:02X}")).collect::<Vec<String>>().join(" ");
+    let stack: Vec<String> = emulator.stack[..emulator.sp as usize].iter().map(|addr| format!("{addr:03X}")).collect();
+
+    format!(
+        "PC {:04X}  I {:04X}  SP {:X}  DT {:02X}  ST {:02X}  V {registers}  Stack [{}]",
+        emulator.pc, emulator.i, emulator.sp, emulator.delay, emulator.sound, stack.join(", ")
+    )
+}
+
+// The registers, stack, timers and next instruction.
+pub fn registers(emulator: &Emulator) -> String {
+    let mut output: String = String::new();
+
+    writeln!(
+        output,
+        "PC {:04X}  I {:04X}  SP {:X}  DT {:02X}  ST {:02X}",
+        emulator.pc, emulator.i, emulator.sp, emulator.delay, emulator.sound
+    ).unwrap();
+
+    let registers: Vec<String> = emulator.vx.iter().enumerate().map(|(n, v)| format!("V{n:X} {v:02X}")).collect();
+    writeln!(output, "{}", registers[..8].join("  ")).unwrap();
+    writeln!(output, "{}", registers[8..].join("  ")).unwrap();
+
+    let stack: Vec<String> = emulator.stack[..emulator.sp as usize].iter().map(|addr| format!("{addr:03X}")).collect();
+    writeln!(output, "Stack [{}]", stack.join(", ")).unwrap();
+
+    match next_instruction(emulator) {
+        Some(instruction) => write!(output, "Next  {:03X}: {instruction}", emulator.pc).unwrap(),
+        None => write!(output, "Next  {:03X}: past the end of memory", emulator.pc).unwrap(),
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Platform;
+    use crate::system::step;
+
+    // A CALL to a subroutine that sets V1 and V2, then a JP to itself forever.
+    fn emulator() -> Emulator {
+        let mut emulator: Emulator = Emulator::new(Platform::Vip);
+        let rom: [u8; 14] = [
+            0x22, 0x08, // 200: CALL 208
+            0x60, 0x01, // 202: LD V0, 1
+            0x12, 0x04, // 204: JP 204
+            0x00, 0x00,
+            0x61, 0x02, // 208: LD V1, 2
+            0x62, 0x03, // 20A: LD V2, 3
+            0x00, 0xEE, // 20C: RET
+        ];
+        emulator.memory[0x200..0x200 + rom.len()].copy_from_slice(&rom);
+        emulator
+    }
+
+    // Runs instructions the way main.rs does until the debugger stops them, or gives up after a while.
+    fn run(debugger: &mut Debugger, emulator: &mut Emulator) {
+        for _ in 0..100 {
+            if debugger.is_paused() || !debugger.before_step(emulator) { return }
+
+            step(emulator).unwrap();
+
+            if debugger.after_step(emulator) { return }
+        }
+    }
+
+    #[test]
+    fn step_runs_one_instruction() {
+        let (mut debugger, mut emulator) = (Debugger::new(true), emulator());
+
+        debugger.command(Command::Step, &emulator);
+        run(&mut debugger, &mut emulator);
+
+        assert!(debugger.is_paused());
+        assert_eq!((emulator.pc, emulator.sp), (0x208, 1));
+    }
+
+    #[test]
+    fn next_steps_over_calls() {
+        let (mut debugger, mut emulator) = (Debugger::new(true), emulator());
+
+        debugger.command(Command::Next, &emulator);
+        run(&mut debugger, &mut emulator);
+
+        assert!(debugger.is_paused());
+        assert_eq!((emulator.pc, emulator.sp), (0x202, 0));
+        assert_eq!(emulator.vx[1..3], [2, 3]);
+    }
+
+    #[test]
+    fn finish_stops_after_ret() {
+        let (mut debugger, mut emulator) = (Debugger::new(true), emulator());
+
+        debugger.command(Command::Step, &emulator);
+        run(&mut debugger, &mut emulator);
+        debugger.command(Command::Step, &emulator);
+        run(&mut debugger, &mut emulator);
+        assert_eq!(emulator.pc, 0x20A);
+
+        debugger.command(Command::Finish, &emulator);
+        run(&mut debugger, &mut emulator);
+
+        assert!(debugger.is_paused());
+        assert_eq!((emulator.pc, emulator.sp), (0x202, 0));
+        assert_eq!(emulator.vx[2], 3);
+        assert_eq!(emulator.vx[0], 0);
+    }
+
+    #[test]
+    fn finish_outside_a_subroutine() {
+        let (mut debugger, emulator) = (Debugger::new(true), emulator());
+
+        assert!(debugger.command(Command::Finish, &emulator).is_some());
+        assert!(debugger.is_paused());
+    }
+
+    #[test]
+    fn continuing_from_a_breakpoint_moves_on() {
+        let (mut debugger, mut emulator) = (Debugger::new(false), emulator());
+        debugger.command(Command::Break(0x20A), &emulator);
+
+        run(&mut debugger, &mut emulator);
+        assert!(debugger.is_paused());
+        assert_eq!((emulator.pc, emulator.vx[2]), (0x20A, 0));
+
+        // The breakpoint isn't hit again on the spot, the subroutine finishes and the loop is reached.
+        debugger.command(Command::Continue, &emulator);
+        run(&mut debugger, &mut emulator);
+        assert!(!debugger.is_paused());
+        assert_eq!((emulator.pc, emulator.vx[2]), (0x204, 3));
+    }
+
+    #[test]
+    fn a_breakpoint_in_a_loop_is_only_skipped_once() {
+        let (mut debugger, mut emulator) = (Debugger::new(false), emulator());
+        debugger.command(Command::Break(0x204), &emulator);
+
+        run(&mut debugger, &mut emulator);
+        assert_eq!(emulator.pc, 0x204);
+        assert!(debugger.is_paused());
+
+        // JP 204 runs once, then the same breakpoint stops it again.
+        debugger.command(Command::Continue, &emulator);
+        assert!(debugger.before_step(&emulator));
+        step(&mut emulator).unwrap();
+        assert!(!debugger.after_step(&emulator));
+        assert!(!debugger.before_step(&emulator));
+        assert!(debugger.is_paused());
+    }
+
+    #[test]
+    fn status_line() {
+        let mut emulator: Emulator = emulator();
+        step(&mut emulator).unwrap();
+
+        assert_eq!(
+            status(&emulator),
+            "PC 0208  I 0000  SP 1  DT 00  ST 00  V 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00  Stack [202]"
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use crate::instruction::{decode, Instruction};
+use crate::platform::Platform;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+// ROMs are loaded at 0x200, see Cowgod's technical reference for the memory.
+const START: usize = 0x0200;
+
+// How many data bytes are printed on each DB line.
+const BYTES_PER_LINE: usize = 8;
+
+// Column the address comments line up on.
+const COMMENT_COLUMN: usize = 32;
+
+// What a label points at, which decides its name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum LabelKind {
+    Sub, // Target of a CALL.
+    Label, // Target of a JP.
+    Data, // Target of an LD I.
+}
+
+// Turns a ROM into assembly, one instruction per line with its address and opcode in a comment.
+// Code is found by following every jump, call and skip from 0x200, anything never reached is printed as DB bytes.
+pub fn disassemble(rom: &[u8], platform: Platform) -> String {
+    let (code, labels) = trace(rom, platform);
+
+    // A label in the middle of an instruction has no line to go on, so those addresses are left as numbers.
+    let inside_instruction = |addr: usize| {
+        code.range(..addr).next_back().is_some_and(|(&start, &length)| addr < start + length)
+    };
+
+    let label_names: BTreeMap<usize, String> = labels
+        .iter()
+        .filter(|(&addr, _)| !inside_instruction(addr))
+        .map(|(&addr, &kind)| (addr, label_name(addr, kind)))
+        .collect();
+
+    let mut output: String = String::new();
+    let mut addr: usize = START;
+    let end: usize = START + rom.len();
+
+    while addr < end {
+        if let Some(name) = label_names.get(&addr) {
+            writeln!(output, "{name}:").unwrap();
+        }
+
+        if let Some(&length) = code.get(&addr) {
+            let opcode: u16 = word(rom, addr).unwrap_or(0);
+            let instruction: Instruction = decode(opcode);
+
+            let text: String = match instruction {
+                Instruction::LoadLongI => {
+                    let nnnn: u16 = word(rom, addr + 2).unwrap_or(0);
+                    format!("LD I, LONG {}", target(nnnn as usize, &label_names, 4))
+                }
+                _ => format_instruction(instruction, &label_names),
+            };
+
+            let raw: String = (0..length).map(|n| format!("{:02X}", rom[addr - START + n])).collect();
+            write_line(&mut output, &text, addr, &raw);
+
+            addr += length;
+        } else {
+            // Data runs until the next instruction or label, whichever comes first.
+            let mut bytes: Vec<String> = Vec::new();
+            let line_start: usize = addr;
+
+            while addr < end && bytes.len() < BYTES_PER_LINE && !code.contains_key(&addr) {
+                if addr != line_start && label_names.contains_key(&addr) { break }
+
+                bytes.push(format!("#{:02X}", rom[addr - START]));
+                addr += 1;
+            }
+
+            write_line(&mut output, &format!("DB {}", bytes.join(", ")), line_start, "");
+        }
+    }
+
+    output
+}
+
+// Follows the program from 0x200, returning where each instruction starts with its length, and the labels it needs.
+fn trace(rom: &[u8], platform: Platform) -> (BTreeMap<usize, usize>, BTreeMap<usize, LabelKind>) {
+    let end: usize = START + rom.len();
+    let in_rom = |addr: usize| (START..end).contains(&addr);
+
+    let mut code: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut covered: BTreeSet<usize> = BTreeSet::new();
+    let mut labels: BTreeMap<usize, LabelKind> = BTreeMap::new();
+    let mut pending: Vec<usize> = vec![START];
+
+    while let Some(addr) = pending.pop() {
+        // Stop at anything already disassembled, or anything that overlaps it.
+        if covered.contains(&addr) || covered.contains(&(addr + 1)) { continue }
+
+        let opcode: u16 = match word(rom, addr) {
+            Some(opcode) => opcode,
+            None => continue,
+        };
+        let instruction: Instruction = decode(opcode);
+
+        // Opcodes the platform doesn't have, and a long load cut off by the end of the ROM, are left as data.
+        let length: usize = if instruction == Instruction::LoadLongI { 4 } else { 2 };
+        if let Instruction::Unknown { .. } = instruction { continue }
+        if !platform.supports(instruction) || addr + length > end { continue }
+        code.insert(addr, length);
+        covered.extend(addr..addr + length);
+
+        let next: usize = addr + length;
+
+        match instruction {
+            Instruction::Jump { nnn } => {
+                add_label(&mut labels, nnn as usize, LabelKind::Label, in_rom);
+                pending.push(nnn as usize);
+            }
+            Instruction::Call { nnn } => {
+                add_label(&mut labels, nnn as usize, LabelKind::Sub, in_rom);
+                pending.push(nnn as usize);
+                pending.push(next);
+            }
+            Instruction::LoadI { nnn } => {
+                add_label(&mut labels, nnn as usize, LabelKind::Data, in_rom);
+                pending.push(next);
+            }
+            Instruction::LoadLongI => {
+                if let Some(nnnn) = word(rom, addr + 2) {
+                    add_label(&mut labels, nnnn as usize, LabelKind::Data, in_rom);
+                }
+                pending.push(next);
+            }
+            Instruction::SkipEqByte { .. }
+            | Instruction::SkipNeByte { .. }
+            | Instruction::SkipEqReg { .. }
+            | Instruction::SkipNeReg { .. }
+            | Instruction::SkipKey { .. }
+            | Instruction::SkipNotKey { .. } => {
+                // XO-CHIP skips over the whole of a four byte instruction.
+                let skipped: usize = match word(rom, next) {
+                    Some(0xF000) if platform.supports_xochip() => 4,
+                    _ => 2,
+                };
+                pending.push(next);
+                pending.push(next + skipped);
+            }
+            // Nothing can be known about where a computed jump goes, and the rest end the current path.
+            Instruction::Ret | Instruction::Exit | Instruction::JumpOffset { .. } => {}
+            _ => pending.push(next),
+        }
+    }
+
+    (code, labels)
+}
+
+// Labels are only made for addresses inside the ROM, a CALL wins over a JP which wins over data.
+fn add_label(labels: &mut BTreeMap<usize, LabelKind>, addr: usize, kind: LabelKind, in_rom: impl Fn(usize) -> bool) {
+    if !in_rom(addr) { return }
+
+    let entry: &mut LabelKind = labels.entry(addr).or_insert(kind);
+    if kind < *entry { *entry = kind }
+}
+
+fn label_name(addr: usize, kind: LabelKind) -> String {
+    match kind {
+        LabelKind::Sub => format!("sub_{addr:03X}"),
+        LabelKind::Label => format!("label_{addr:03X}"),
+        LabelKind::Data => format!("data_{addr:03X}"),
+    }
+}
+
+// The label for an address if it has one, otherwise the address in hex.
+fn target(addr: usize, labels: &BTreeMap<usize, String>, digits: usize) -> String {
+    match labels.get(&addr) {
+        Some(name) => name.clone(),
+        None => format!("#{addr:0digits$X}"),
+    }
+}
+
+// Same as the instruction's Display, but with addresses swapped for labels.
+fn format_instruction(instruction: Instruction, labels: &BTreeMap<usize, String>) -> String {
+    match instruction {
+        Instruction::Jump { nnn } => format!("JP {}", target(nnn as usize, labels, 3)),
+        Instruction::Call { nnn } => format!("CALL {}", target(nnn as usize, labels, 3)),
+        Instruction::LoadI { nnn } => format!("LD I, {}", target(nnn as usize, labels, 3)),
+        Instruction::JumpOffset { nnn } => format!("JP V0, {}", target(nnn as usize, labels, 3)),
+        _ => instruction.to_string(),
+    }
+}
+
+// Writes an indented line with the address, and raw bytes if there are any, in a comment.
+fn write_line(output: &mut String, text: &str, addr: usize, raw: &str) {
+    let line: String = format!("    {text}");
+    let comment: String = if raw.is_empty() { format!("{addr:03X}") } else { format!("{addr:03X}: {raw}") };
+
+    writeln!(output, "{line:<COMMENT_COLUMN$} ; {comment}").unwrap();
+}
+
+// Reads a big endian word from the ROM at a memory address.
+fn word(rom: &[u8], addr: usize) -> Option<u16> {
+    let offset: usize = addr.checked_sub(START)?;
+    let high: u8 = *rom.get(offset)?;
+    let low: u8 = *rom.get(offset + 1)?;
+
+    Some((high as u16) << 8 | low as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // F000 with nothing after it, or on a platform without XO-CHIP, is printed as bytes instead of read past the end.
+    #[test]
+    fn long_load_that_doesnt_fit_is_data() {
+        let cut_off: String = disassemble(&[0x00, 0xE0, 0xF0, 0x00], Platform::XoChip);
+        assert!(cut_off.contains("CLS") && cut_off.contains("DB #F0, #00"), "{cut_off}");
+
+        let vip: String = disassemble(&[0xF0, 0x00], Platform::Vip);
+        assert!(vip.contains("DB #F0, #00"), "{vip}");
+
+        let fits: String = disassemble(&[0xF0, 0x00, 0x02, 0x00], Platform::XoChip);
+        assert!(fits.contains("LD I, LONG"), "{fits}");
+    }
+
+    #[test]
+    fn other_platforms_opcodes_are_data() {
+        let schip: String = disassemble(&[0x00, 0xFF, 0x00, 0xFD], Platform::Vip);
+        assert!(!schip.contains("HIGH") && schip.contains("DB #00, #FF, #00, #FD"), "{schip}");
+    }
+}
@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// Everything that can go wrong while loading or running a ROM.
+#[derive(Debug)]
+pub enum EmulatorError {
+    StackOverflow { pc: u16 }, // 0x2nnn with a full stack.
+    StackUnderflow { pc: u16 }, // 0x00EE with an empty stack.
+    MemoryOutOfBounds { addr: usize }, // Read or write past the end of memory.
+    UnknownOpcode { pc: u16, opcode: u16 }, // Instruction the platform doesn't have.
+    RomTooLarge { size: usize, max: usize }, // ROM doesn't fit between 0x200 and the end of memory.
+    BadSaveState { reason: String }, // Save state is from another version or isn't one at all.
+    BadMovie { reason: String }, // Movie file is from another version or isn't one at all.
+    BadConfig { reason: String }, // Config file has a mistake in it.
+    Io(io::Error), // ROM, save state or movie file couldn't be read.
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmulatorError::StackOverflow { pc } => write!(f, "stack overflow calling a subroutine at {pc:03X}"),
+            EmulatorError::StackUnderflow { pc } => write!(f, "stack underflow returning from a subroutine at {pc:03X}"),
+            EmulatorError::MemoryOutOfBounds { addr } => write!(f, "memory access out of bounds at {addr:04X}"),
+            EmulatorError::UnknownOpcode { pc, opcode } => write!(f, "unknown opcode {opcode:04X} at {pc:03X}"),
+            EmulatorError::RomTooLarge { size, max } => write!(f, "ROM is {size} bytes but only {max} bytes fit in memory"),
+            EmulatorError::BadSaveState { reason } => write!(f, "can't load save state: {reason}"),
+            EmulatorError::BadMovie { reason } => write!(f, "can't load movie: {reason}"),
+            EmulatorError::BadConfig { reason } => write!(f, "invalid config: {reason}"),
+            EmulatorError::Io(error) => write!(f, "problem opening file: {error}"),
+        }
+    }
+}
+
+impl Error for EmulatorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EmulatorError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for EmulatorError {
+    fn from(error: io::Error) -> Self {
+        EmulatorError::Io(error)
+    }
+}
+
+// Image encoders only fail on write errors or on dimensions this code never produces, so both count as I/O.
+impl From<png::EncodingError> for EmulatorError {
+    fn from(error: png::EncodingError) -> Self {
+        match error {
+            png::EncodingError::IoError(error) => EmulatorError::Io(error),
+            error => EmulatorError::Io(io::Error::other(error)),
+        }
+    }
+}
+
+impl From<gif::EncodingError> for EmulatorError {
+    fn from(error: gif::EncodingError) -> Self {
+        match error {
+            gif::EncodingError::Io(error) => EmulatorError::Io(error),
+            error => EmulatorError::Io(io::Error::other(error)),
+        }
+    }
+}
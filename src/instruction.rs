@@ -0,0 +1,235 @@
+use std::fmt;
+
+// Every instruction the interpreter knows about, with its operands already pulled out of the opcode.
+// Register operands are indices into Emulator::vx.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    ScrollDown { n: u8 }, // 00Cn (SUPER-CHIP)
+    ScrollUp { n: u8 }, // 00Dn (XO-CHIP)
+    Cls, // 00E0
+    Ret, // 00EE
+    ScrollRight, // 00FB (SUPER-CHIP)
+    ScrollLeft, // 00FC (SUPER-CHIP)
+    Exit, // 00FD (SUPER-CHIP)
+    LowRes, // 00FE (SUPER-CHIP)
+    HighRes, // 00FF (SUPER-CHIP)
+    Jump { nnn: u16 }, // 1nnn
+    Call { nnn: u16 }, // 2nnn
+    SkipEqByte { x: usize, kk: u8 }, // 3xkk
+    SkipNeByte { x: usize, kk: u8 }, // 4xkk
+    SkipEqReg { x: usize, y: usize }, // 5xy0
+    SaveRange { x: usize, y: usize }, // 5xy2 (XO-CHIP)
+    LoadRange { x: usize, y: usize }, // 5xy3 (XO-CHIP)
+    LoadByte { x: usize, kk: u8 }, // 6xkk
+    AddByte { x: usize, kk: u8 }, // 7xkk
+    Move { x: usize, y: usize }, // 8xy0
+    Or { x: usize, y: usize }, // 8xy1
+    And { x: usize, y: usize }, // 8xy2
+    Xor { x: usize, y: usize }, // 8xy3
+    Add { x: usize, y: usize }, // 8xy4
+    Sub { x: usize, y: usize }, // 8xy5
+    ShiftRight { x: usize, y: usize }, // 8xy6
+    SubN { x: usize, y: usize }, // 8xy7
+    ShiftLeft { x: usize, y: usize }, // 8xyE
+    SkipNeReg { x: usize, y: usize }, // 9xy0
+    LoadI { nnn: u16 }, // Annn
+    JumpOffset { nnn: u16 }, // Bnnn
+    Random { x: usize, kk: u8 }, // Cxkk
+    Draw { x: usize, y: usize, n: u8 }, // Dxyn
+    SkipKey { x: usize }, // Ex9E
+    SkipNotKey { x: usize }, // ExA1
+    LoadLongI, // F000 nnnn (XO-CHIP), the address is the next word in memory.
+    Plane { n: u8 }, // Fn01 (XO-CHIP)
+    LoadAudio, // F002 (XO-CHIP)
+    LoadDelay { x: usize }, // Fx07
+    WaitKey { x: usize }, // Fx0A
+    SetDelay { x: usize }, // Fx15
+    SetSound { x: usize }, // Fx18
+    AddI { x: usize }, // Fx1E
+    Font { x: usize }, // Fx29
+    BigFont { x: usize }, // Fx30 (SUPER-CHIP)
+    Bcd { x: usize }, // Fx33
+    Pitch { x: usize }, // Fx3A (XO-CHIP)
+    Store { x: usize }, // Fx55
+    Load { x: usize }, // Fx65
+    SaveFlags { x: usize }, // Fx75 (SUPER-CHIP)
+    LoadFlags { x: usize }, // Fx85 (SUPER-CHIP)
+    Unknown { opcode: u16 },
+}
+
+// Parses an opcode into an instruction. Nothing is executed, so this is safe to run over any data.
+pub fn decode(opcode: u16) -> Instruction {
+    // Common parts of instructions for matching.
+    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
+    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
+    let n: u8 = (opcode & 0x000F) as u8;
+    let kk: u8 = (opcode & 0x00FF) as u8;
+    let nnn: u16 = opcode & 0x0FFF;
+
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00C0..=0x00CF => Instruction::ScrollDown { n },
+            0x00D0..=0x00DF => Instruction::ScrollUp { n },
+            0x00E0 => Instruction::Cls,
+            0x00EE => Instruction::Ret,
+            0x00FB => Instruction::ScrollRight,
+            0x00FC => Instruction::ScrollLeft,
+            0x00FD => Instruction::Exit,
+            0x00FE => Instruction::LowRes,
+            0x00FF => Instruction::HighRes,
+            _ => Instruction::Unknown { opcode },
+        },
+        0x1000 => Instruction::Jump { nnn },
+        0x2000 => Instruction::Call { nnn },
+        0x3000 => Instruction::SkipEqByte { x, kk },
+        0x4000 => Instruction::SkipNeByte { x, kk },
+        0x5000 => match n {
+            0x0 => Instruction::SkipEqReg { x, y },
+            0x2 => Instruction::SaveRange { x, y },
+            0x3 => Instruction::LoadRange { x, y },
+            _ => Instruction::Unknown { opcode },
+        },
+        0x6000 => Instruction::LoadByte { x, kk },
+        0x7000 => Instruction::AddByte { x, kk },
+        0x8000 => match n {
+            0x0 => Instruction::Move { x, y },
+            0x1 => Instruction::Or { x, y },
+            0x2 => Instruction::And { x, y },
+            0x3 => Instruction::Xor { x, y },
+            0x4 => Instruction::Add { x, y },
+            0x5 => Instruction::Sub { x, y },
+            0x6 => Instruction::ShiftRight { x, y },
+            0x7 => Instruction::SubN { x, y },
+            0xE => Instruction::ShiftLeft { x, y },
+            _ => Instruction::Unknown { opcode },
+        },
+        0x9000 if n == 0 => Instruction::SkipNeReg { x, y },
+        0xA000 => Instruction::LoadI { nnn },
+        0xB000 => Instruction::JumpOffset { nnn },
+        0xC000 => Instruction::Random { x, kk },
+        0xD000 => Instruction::Draw { x, y, n },
+        0xE000 => match kk {
+            0x9E => Instruction::SkipKey { x },
+            0xA1 => Instruction::SkipNotKey { x },
+            _ => Instruction::Unknown { opcode },
+        },
+        0xF000 => match kk {
+            0x00 if x == 0 => Instruction::LoadLongI,
+            0x01 => Instruction::Plane { n: x as u8 },
+            0x02 if x == 0 => Instruction::LoadAudio,
+            0x07 => Instruction::LoadDelay { x },
+            0x0A => Instruction::WaitKey { x },
+            0x15 => Instruction::SetDelay { x },
+            0x18 => Instruction::SetSound { x },
+            0x1E => Instruction::AddI { x },
+            0x29 => Instruction::Font { x },
+            0x30 => Instruction::BigFont { x },
+            0x33 => Instruction::Bcd { x },
+            0x3A => Instruction::Pitch { x },
+            0x55 => Instruction::Store { x },
+            0x65 => Instruction::Load { x },
+            0x75 => Instruction::SaveFlags { x },
+            0x85 => Instruction::LoadFlags { x },
+            _ => Instruction::Unknown { opcode },
+        },
+        _ => Instruction::Unknown { opcode },
+    }
+}
+
+impl Instruction {
+    // Assembly mnemonic, following Cowgod's technical reference where it has one.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::ScrollDown { .. } => "SCD",
+            Instruction::ScrollUp { .. } => "SCU",
+            Instruction::Cls => "CLS",
+            Instruction::Ret => "RET",
+            Instruction::ScrollRight => "SCR",
+            Instruction::ScrollLeft => "SCL",
+            Instruction::Exit => "EXIT",
+            Instruction::LowRes => "LOW",
+            Instruction::HighRes => "HIGH",
+            Instruction::Jump { .. } | Instruction::JumpOffset { .. } => "JP",
+            Instruction::Call { .. } => "CALL",
+            Instruction::SkipEqByte { .. } | Instruction::SkipEqReg { .. } => "SE",
+            Instruction::SkipNeByte { .. } | Instruction::SkipNeReg { .. } => "SNE",
+            Instruction::SaveRange { .. } => "SAVE",
+            Instruction::LoadRange { .. } => "LOAD",
+            Instruction::AddByte { .. } | Instruction::Add { .. } | Instruction::AddI { .. } => "ADD",
+            Instruction::Or { .. } => "OR",
+            Instruction::And { .. } => "AND",
+            Instruction::Xor { .. } => "XOR",
+            Instruction::Sub { .. } => "SUB",
+            Instruction::ShiftRight { .. } => "SHR",
+            Instruction::SubN { .. } => "SUBN",
+            Instruction::ShiftLeft { .. } => "SHL",
+            Instruction::Random { .. } => "RND",
+            Instruction::Draw { .. } => "DRW",
+            Instruction::SkipKey { .. } => "SKP",
+            Instruction::SkipNotKey { .. } => "SKNP",
+            Instruction::Plane { .. } => "PLANE",
+            Instruction::LoadAudio => "AUDIO",
+            Instruction::Pitch { .. } => "PITCH",
+            Instruction::Unknown { .. } => "DW",
+            _ => "LD",
+        }
+    }
+
+    // Operands in assembly syntax, immediate values are hex prefixed with #.
+    pub fn operands(&self) -> String {
+        match *self {
+            Instruction::ScrollDown { n } | Instruction::ScrollUp { n } | Instruction::Plane { n } => format!("#{n:X}"),
+            Instruction::Jump { nnn } | Instruction::Call { nnn } => format!("#{nnn:03X}"),
+            Instruction::SkipEqByte { x, kk }
+            | Instruction::SkipNeByte { x, kk }
+            | Instruction::LoadByte { x, kk }
+            | Instruction::AddByte { x, kk }
+            | Instruction::Random { x, kk } => format!("V{x:X}, #{kk:02X}"),
+            Instruction::SkipEqReg { x, y }
+            | Instruction::SaveRange { x, y }
+            | Instruction::LoadRange { x, y }
+            | Instruction::Move { x, y }
+            | Instruction::Or { x, y }
+            | Instruction::And { x, y }
+            | Instruction::Xor { x, y }
+            | Instruction::Add { x, y }
+            | Instruction::Sub { x, y }
+            | Instruction::ShiftRight { x, y }
+            | Instruction::SubN { x, y }
+            | Instruction::ShiftLeft { x, y }
+            | Instruction::SkipNeReg { x, y } => format!("V{x:X}, V{y:X}"),
+            Instruction::LoadI { nnn } => format!("I, #{nnn:03X}"),
+            Instruction::JumpOffset { nnn } => format!("V0, #{nnn:03X}"),
+            Instruction::Draw { x, y, n } => format!("V{x:X}, V{y:X}, #{n:X}"),
+            Instruction::SkipKey { x } | Instruction::SkipNotKey { x } | Instruction::Pitch { x } => format!("V{x:X}"),
+            Instruction::LoadLongI => "I, LONG".to_string(),
+            Instruction::LoadDelay { x } => format!("V{x:X}, DT"),
+            Instruction::WaitKey { x } => format!("V{x:X}, K"),
+            Instruction::SetDelay { x } => format!("DT, V{x:X}"),
+            Instruction::SetSound { x } => format!("ST, V{x:X}"),
+            Instruction::AddI { x } => format!("I, V{x:X}"),
+            Instruction::Font { x } => format!("F, V{x:X}"),
+            Instruction::BigFont { x } => format!("HF, V{x:X}"),
+            Instruction::Bcd { x } => format!("B, V{x:X}"),
+            Instruction::Store { x } => format!("[I], V{x:X}"),
+            Instruction::Load { x } => format!("V{x:X}, [I]"),
+            Instruction::SaveFlags { x } => format!("R, V{x:X}"),
+            Instruction::LoadFlags { x } => format!("V{x:X}, R"),
+            Instruction::Unknown { opcode } => format!("#{opcode:04X}"),
+            _ => String::new(),
+        }
+    }
+}
+
+// Formats the instruction as one line of assembly, e.g. "DRW V0, V1, #5".
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operands: String = self.operands();
+
+        if operands.is_empty() {
+            write!(f, "{}", self.mnemonic())
+        } else {
+            write!(f, "{} {}", self.mnemonic(), operands)
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::error::EmulatorError;
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// The COSMAC VIP's hex keypad, row by row. Presets list host keys in the same order, so they keep its shape.
+const KEYPAD: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC,
+    0x4, 0x5, 0x6, 0xD,
+    0x7, 0x8, 0x9, 0xE,
+    0xA, 0x0, 0xB, 0xF,
+];
+
+// Built in layouts, named for --layout and the preset key in a config file.
+// Host keys use SDL2's key names, which are what the keyboard layout prints on the key.
+pub const PRESETS: [(&str, [&str; 16]); 5] = [
+    ("qwerty", [
+        "1", "2", "3", "4",
+        "Q", "W", "E", "R",
+        "A", "S", "D", "F",
+        "Z", "X", "C", "V",
+    ]),
+    ("azerty", [
+        "&", "é", "\"", "'",
+        "A", "Z", "E", "R",
+        "Q", "S", "D", "F",
+        "W", "X", "C", "V",
+    ]),
+    ("qwertz", [
+        "1", "2", "3", "4",
+        "Q", "W", "E", "R",
+        "A", "S", "D", "F",
+        "Y", "X", "C", "V",
+    ]),
+    ("dvorak", [
+        "1", "2", "3", "4",
+        "'", ",", ".", "P",
+        "A", "O", "E", "U",
+        ";", "Q", "J", "K",
+    ]),
+    // The digits are on the matching number pad keys, A to F on the keys around them.
+    ("numpad", [
+        "Keypad 1", "Keypad 2", "Keypad 3", "Keypad -",
+        "Keypad 4", "Keypad 5", "Keypad 6", "Keypad +",
+        "Keypad 7", "Keypad 8", "Keypad 9", "Keypad Enter",
+        "Keypad /", "Keypad 0", "Keypad *", "Keypad .",
+    ]),
+];
+
+// Game controller buttons, using SDL2's names. Most games only need the arrows on 2, 4, 6 and 8 and
+// something to fire with, 5 being the most common.
+pub const DEFAULT_BUTTONS: [(u8, &str); 7] = [
+    (0x2, "dpup"),
+    (0x8, "dpdown"),
+    (0x4, "dpleft"),
+    (0x6, "dpright"),
+    (0x5, "a"),
+    (0x0, "b"),
+    (0xF, "start"),
+];
+
+// Which host keys and controller buttons press each CHIP-8 key, any number of them can share one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyMap {
+    pub keys: [Vec<String>; 16], // Host key names, indexed by CHIP-8 key.
+    pub buttons: [Vec<String>; 16], // Controller button names, indexed by CHIP-8 key.
+}
+
+impl KeyMap {
+    pub fn preset(name: &str) -> Option<KeyMap> {
+        let (_, layout) = PRESETS.iter().find(|(preset, _)| preset.eq_ignore_ascii_case(name))?;
+
+        let mut keys: [Vec<String>; 16] = Default::default();
+        for (position, host) in layout.iter().enumerate() {
+            keys[KEYPAD[position] as usize].push(host.to_string());
+        }
+
+        let mut buttons: [Vec<String>; 16] = Default::default();
+        for (key, button) in DEFAULT_BUTTONS {
+            buttons[key as usize].push(button.to_string());
+        }
+
+        Some(KeyMap { keys, buttons })
+    }
+
+    // The CHIP-8 key a host key presses, names are matched ignoring case.
+    pub fn chip8_key(&self, host: &str) -> Option<u8> {
+        find(&self.keys, host)
+    }
+
+    // The CHIP-8 key a controller button presses.
+    pub fn chip8_button(&self, button: &str) -> Option<u8> {
+        find(&self.buttons, button)
+    }
+
+    // Moves a controller button to a CHIP-8 key, taking it off whichever key it pressed before.
+    pub fn bind_button(&mut self, button: &str, key: u8) {
+        unbind(&mut self.buttons, &[button.to_string()]);
+        self.buttons[key as usize & 0x0F].push(button.to_string());
+    }
+}
+
+// Takes names off every CHIP-8 key they're bound to, so they can be given to another.
+fn unbind(bindings: &mut [Vec<String>; 16], names: &[String]) {
+    for bound in bindings.iter_mut() {
+        bound.retain(|binding| !names.iter().any(|name| name.eq_ignore_ascii_case(binding)));
+    }
+}
+
+fn find(bindings: &[Vec<String>; 16], name: &str) -> Option<u8> {
+    let name: String = name.to_lowercase();
+
+    (0..16).find(|&key| bindings[key as usize].iter().any(|binding| binding.to_lowercase() == name))
+}
+
+// Default is the QWERTY layout the interpreter has always used.
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap::preset("qwerty").unwrap()
+    }
+}
+
+// A key bindings file, e.g.
+//
+//     preset = "azerty"
+//
+//     [keys]
+//     5 = ["Z", "Up"]
+//
+//     [buttons]
+//     5 = ["a", "x"]
+//
+//     [roms."caveexplorer.ch8".keys]
+//     5 = ["Up"]
+//
+// Keys listed under [keys] replace the preset's keys for those CHIP-8 keys and stop pressing whatever key they
+// pressed before, [buttons] does the same for controller buttons, and a ROM's own section is applied on top of
+// that when its file name matches. A preset in a ROM's section starts that ROM's keys over from the preset.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyConfig {
+    pub preset: Option<String>,
+
+    #[serde(default)]
+    pub keys: BTreeMap<String, Vec<String>>, // CHIP-8 key in hex to host key names.
+
+    #[serde(default)]
+    pub buttons: BTreeMap<String, Vec<String>>, // CHIP-8 key in hex to controller button names.
+
+    #[serde(default)]
+    pub roms: BTreeMap<String, Bindings>, // Overrides by ROM file name.
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bindings {
+    pub preset: Option<String>,
+
+    #[serde(default)]
+    pub keys: BTreeMap<String, Vec<String>>, // CHIP-8 key in hex to host key names.
+
+    #[serde(default)]
+    pub buttons: BTreeMap<String, Vec<String>>, // CHIP-8 key in hex to controller button names.
+}
+
+impl KeyConfig {
+    pub fn load(path: &Path) -> Result<KeyConfig, EmulatorError> {
+        let text: String = fs::read_to_string(path)?;
+
+        toml::from_str(&text).map_err(|error| EmulatorError::BadConfig { reason: error.message().to_string() })
+    }
+
+    // Key bindings for a ROM. A layout from the command line wins over any preset in the file,
+    // without either it's QWERTY.
+    pub fn keymap(&self, rom: &str, layout: Option<&str>) -> Result<KeyMap, EmulatorError> {
+        let mut keymap: KeyMap = preset(layout.unwrap_or("qwerty"))?;
+
+        let rom_bindings = self.roms.get(rom).map(|bindings| (&bindings.preset, &bindings.keys, &bindings.buttons));
+
+        for (file_layout, keys, buttons) in [Some((&self.preset, &self.keys, &self.buttons)), rom_bindings].into_iter().flatten() {
+            if let (Some(name), None) = (file_layout, layout) {
+                keymap.keys = preset(name)?.keys;
+            }
+
+            for (key, hosts) in keys {
+                let key: usize = chip8_key(key)?;
+                unbind(&mut keymap.keys, hosts);
+                keymap.keys[key] = hosts.clone();
+            }
+            for (key, names) in buttons {
+                let key: usize = chip8_key(key)?;
+                unbind(&mut keymap.buttons, names);
+                keymap.buttons[key] = names.clone();
+            }
+        }
+
+        Ok(keymap)
+    }
+}
+
+// Parses a CHIP-8 key from a config file, written in hex.
+fn chip8_key(key: &str) -> Result<usize, EmulatorError> {
+    u8::from_str_radix(key, 16)
+        .ok()
+        .filter(|&key| key < 16)
+        .map(|key| key as usize)
+        .ok_or_else(|| bad_config(&format!("'{key}' isn't a CHIP-8 key, expected 0 to F")))
+}
+
+fn preset(name: &str) -> Result<KeyMap, EmulatorError> {
+    KeyMap::preset(name).ok_or_else(|| {
+        let names: Vec<&str> = PRESETS.iter().map(|(name, _)| *name).collect();
+        bad_config(&format!("unknown layout '{name}', expected {}", names.join(", ")))
+    })
+}
+
+fn bad_config(reason: &str) -> EmulatorError {
+    EmulatorError::BadConfig { reason: reason.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(text: &str) -> KeyConfig {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn overrides_take_keys_off_their_old_key() {
+        let keymap: KeyMap = config("[keys]\nC = [\"Q\"]\n\n[buttons]\n1 = [\"a\"]").keymap("game.ch8", None).unwrap();
+
+        assert_eq!(keymap.chip8_key("q"), Some(0xC));
+        assert_eq!(keymap.chip8_key("4"), None);
+        assert!(keymap.keys[0x4].is_empty());
+        assert_eq!(keymap.chip8_button("a"), Some(0x1));
+        assert!(keymap.buttons[0x5].is_empty());
+    }
+
+    #[test]
+    fn rom_sections_go_on_top() {
+        let text: &str = "[keys]\n5 = [\"Up\"]\n\n[roms.\"game.ch8\".keys]\n8 = [\"Up\"]";
+
+        assert_eq!(config(text).keymap("game.ch8", None).unwrap().chip8_key("Up"), Some(0x8));
+        assert_eq!(config(text).keymap("other.ch8", None).unwrap().chip8_key("Up"), Some(0x5));
+    }
+
+    #[test]
+    fn command_line_layout_wins_over_presets() {
+        let text: &str = "preset = \"azerty\"\n\n[roms.\"game.ch8\"]\npreset = \"numpad\"";
+
+        assert_eq!(config(text).keymap("other.ch8", None).unwrap().chip8_key("A"), Some(0x4));
+        assert_eq!(config(text).keymap("game.ch8", None).unwrap().chip8_key("Keypad 5"), Some(0x5));
+        assert_eq!(config(text).keymap("game.ch8", Some("qwerty")).unwrap().chip8_key("Q"), Some(0x4));
+        assert_eq!(config(text).keymap("game.ch8", Some("dvorak")).unwrap(), KeyMap::preset("dvorak").unwrap());
+    }
+}
@@ -1,2 +1,21 @@
+pub mod asm;
+pub mod audio;
+pub mod clock;
+pub mod database;
+pub mod debugger;
+pub mod disasm;
+pub mod error;
+pub mod instruction;
+pub mod keymap;
+pub mod movie;
 pub mod opcodes;
-pub mod system;
\ No newline at end of file
+pub mod palette;
+pub mod persistence;
+pub mod platform;
+pub mod quirks;
+pub mod random;
+pub mod rewind;
+pub mod screenshot;
+pub mod state;
+pub mod system;
+pub mod video;
\ No newline at end of file
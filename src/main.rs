@@ -1,164 +1,938 @@
+use lolei_chip8::asm::assemble_file;
+use lolei_chip8::audio::{Tone, Waveform};
+use lolei_chip8::clock::{Clock, TIMER_HZ};
+use lolei_chip8::database::{Database, RomSettings};
+use lolei_chip8::debugger::{parse_command, registers, status, Debugger};
+use lolei_chip8::disasm::disassemble;
+use lolei_chip8::error::EmulatorError;
+use lolei_chip8::instruction::{decode, Instruction};
+use lolei_chip8::keymap::{KeyConfig, KeyMap};
+use lolei_chip8::movie::Movie;
+use lolei_chip8::palette::{add_themes, load_themes, overlay, parse_colours, Palette, DEFAULT_PALETTE, THEMES};
+use lolei_chip8::persistence::Persistence;
+use lolei_chip8::platform::Platform;
+use lolei_chip8::quirks::Quirks;
+use lolei_chip8::random::VipRandom;
+use lolei_chip8::rewind::Rewind;
+use lolei_chip8::screenshot::{write_pbm, write_png};
 use lolei_chip8::system::*;
+use lolei_chip8::video::VideoRecorder;
 
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::controller::{Axis, GameController};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::render::Canvas;
-use sdl2::video::Window;
+use sdl2::video::{FullscreenType, Window};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use std::time::{Duration, Instant};
+use sdl2::EventPump;
+use std::fs::File;
+use std::io::{BufRead, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Instant;
 use std::thread;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the ROM file, e.g. roms/2-ibm-logo.ch8
+    #[arg(short, long, required_unless_present = "play")]
+    rom: Option<String>,
+
+    /// Platform to emulate: vip, chip48, schip1.0, schip1.1 or xochip. Defaults to the ROM database's pick, or vip
+    #[arg(short, long)]
+    platform: Option<Platform>,
+
+    /// Quirk profile to use instead of the platform's: vip, chip48, schip or xochip
     #[arg(short, long)]
-    rom: String,
+    quirks: Option<Quirks>,
+
+    /// Print every instruction as it's executed
+    #[arg(long)]
+    trace: bool,
+
+    /// Override a single quirk, e.g. --quirk shifting=on. Can be repeated
+    #[arg(long = "quirk", value_name = "NAME=on|off")]
+    quirk: Vec<String>,
+
+    /// Start paused with the step debugger reading commands from the terminal
+    #[arg(long)]
+    debug: bool,
+
+    /// Pause when the program counter reaches a hex address, e.g. --break 20A. Can be repeated
+    #[arg(long = "break", value_name = "ADDR", value_parser = parse_address)]
+    breakpoints: Vec<u16>,
+
+    /// Seed for Cxkk's random numbers, the same seed gives the same numbers every run
+    #[arg(long, conflicts_with = "play")]
+    seed: Option<u64>,
+
+    /// Use a timing dependent random number routine like the COSMAC VIP's for Cxkk instead of a seeded generator
+    #[arg(long, conflicts_with_all = ["record", "play"])]
+    vip_random: bool,
+
+    /// Record the keys held on every frame to a movie file that --play can replay exactly
+    #[arg(long, value_name = "MOVIE", conflicts_with_all = ["play", "debug", "breakpoints"])]
+    record: Option<PathBuf>,
+
+    /// Keyboard layout for the keypad: qwerty, azerty, qwertz, dvorak or numpad, defaults to qwerty
+    #[arg(long)]
+    layout: Option<String>,
+
+    /// TOML file with key bindings, see the README
+    #[arg(long, value_name = "FILE")]
+    keys: Option<PathBuf>,
+
+    /// Pitch of the buzzer in Hz
+    #[arg(long, default_value_t = 440.0)]
+    frequency: f32,
+
+    /// Volume of the buzzer from 0 to 100
+    #[arg(long, default_value_t = 25, value_parser = clap::value_parser!(u8).range(0..=100))]
+    volume: u8,
+
+    /// Shape of the buzzer's tone: square, sine, triangle or sawtooth
+    #[arg(long, default_value = "square")]
+    waveform: Waveform,
+
+    /// Start with the sound off, M toggles it
+    #[arg(long)]
+    mute: bool,
+
+    /// Size of a CHIP-8 pixel in the starting window, and in recorded videos
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=64))]
+    scale: u32,
+
+    /// Start fullscreen, Alt + Enter switches between fullscreen and a window
+    #[arg(long)]
+    fullscreen: bool,
+
+    /// Fade erased pixels out over a few frames to hide flicker, from 0 (off) to 95 (slowest)
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=95))]
+    persistence: u8,
+
+    /// Instructions run per second, instead of the platform's or the ROM database's speed, from 60 to 1000000 with anything outside raised or lowered to fit. F7 and F8 change it while running
+    #[arg(long, value_parser = clap::value_parser!(u32).range(60..=1_000_000), conflicts_with = "play")]
+    ips: Option<u32>,
+
+    /// Colour theme: amber, green, lcd, white or one from --themes. F5 switches between them
+    #[arg(long, default_value = "amber")]
+    theme: String,
+
+    /// TOML file with more colour themes, see the README
+    #[arg(long, value_name = "FILE")]
+    themes: Option<PathBuf>,
+
+    /// Comma separated #RRGGBB colours to use in place of the theme's, background first
+    #[arg(long, value_name = "COLOURS")]
+    colours: Option<String>,
+
+    /// Record the screen to an animated .gif or .png (APNG) file, F11 starts and stops recording too
+    #[arg(long, value_name = "FILE")]
+    record_video: Option<PathBuf>,
+
+    /// Folder with programs.json and sha1-hashes.json from the CHIP-8 database, instead of the built in copy
+    #[arg(long, value_name = "DIR")]
+    database: Option<PathBuf>,
+
+    /// Replay a movie made with --record, the keyboard takes over once it ends
+    #[arg(long, value_name = "MOVIE", conflicts_with_all = ["debug", "breakpoints"])]
+    play: Option<PathBuf>,
 }
 
-// Map sdl2 keycodes to chip8 keycodes.
-fn map_keys(key: Keycode) -> Option<u8> {
+// Parses a hex address for --break, with or without a 0x or # prefix.
+fn parse_address(text: &str) -> Result<u16, String> {
+    let hex: &str = text.trim_start_matches("0x").trim_start_matches('#');
+
+    u16::from_str_radix(hex, 16).map_err(|_| format!("'{text}' isn't a hex address"))
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print a ROM as assembly, with labels for jump and call targets
+    Disasm {
+        /// Path to the ROM file, e.g. roms/2-ibm-logo.ch8
+        rom: String,
+
+        /// Platform the ROM was written for: vip, chip48, schip1.0, schip1.1 or xochip
+        #[arg(short, long, default_value = "vip")]
+        platform: Platform,
+    },
+
+    /// Assemble a source file into a ROM that can be run with --rom
+    Asm {
+        /// Path to the assembly source, e.g. tests/draw.asm
+        source: PathBuf,
+
+        /// Where to write the ROM, defaults to the source path with a .ch8 extension
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+// SDL2 frontend, owns the window and everything else the emulator core doesn't need to know about.
+struct Frontend {
+    canvas: Canvas<Window>, // Canvas the display array is drawn to.
+    event_pump: EventPump, // Event pump for checking keypresses.
+    audio: Option<AudioDevice<Buzzer>>, // None if there's no audio device, the game still runs silently.
+    controller_subsystem: sdl2::GameControllerSubsystem, // For opening game controllers as they're plugged in.
+    controllers: Vec<GameController>, // Open controllers, they stop sending events once dropped.
+    palette: Palette, // Colours for each combination of planes.
+    video_width: usize, // Width of recorded videos, what the window starts at.
+    persistence: Option<Persistence>, // Fades erased pixels out instead of them vanishing, if asked for.
+}
+
+// Hands the tone to SDL2, which calls back from its own thread whenever it needs more samples.
+struct Buzzer(Tone);
+
+impl AudioCallback for Buzzer {
+    type Channel = f32;
+
+    fn callback(&mut self, output: &mut [f32]) {
+        self.0.fill(output);
+    }
+}
+
+impl Frontend {
+    fn new(scale: u32, fullscreen: bool, frequency: f32, volume: f32, waveform: Waveform, muted: bool) -> Result<Self, String> {
+        // SDL2 variables for setting up the window and canvas.
+        let sdl_content: sdl2::Sdl = sdl2::init()?;
+        let video_subsystem: sdl2::VideoSubsystem = sdl_content.video()?;
+
+        // The window starts at the scale asked for and can be resized freely, the display keeps its shape inside it.
+        let mut window: Window = video_subsystem
+                    .window("Chip-8 Interpreter", 64 * scale, 32 * scale)
+                    .position_centered()
+                    .resizable()
+                    .build()
+                    .map_err(|e| e.to_string())?;
+
+        if fullscreen {
+            window.set_fullscreen(FullscreenType::Desktop)?;
+        }
+
+        let canvas: Canvas<Window> = window.into_canvas().build().map_err(|e| e.to_string())?;
+        let event_pump: EventPump = sdl_content.event_pump()?;
+
+        // 44.1kHz mono, with a small buffer so the beep starts and stops close to the sound timer.
+        let desired: AudioSpecDesired = AudioSpecDesired { freq: Some(44100), channels: Some(1), samples: Some(512) };
+        let audio: Option<AudioDevice<Buzzer>> = sdl_content
+            .audio()
+            .and_then(|audio| audio.open_playback(None, &desired, |spec| {
+                let mut tone: Tone = Tone::new(spec.freq as u32, frequency, volume, waveform);
+                tone.muted = muted;
+                Buzzer(tone)
+            }))
+            .inspect(|device| device.resume())
+            .inspect_err(|error| eprintln!("No sound: {error}"))
+            .ok();
+
+        // Controllers already plugged in get an added event on startup, so they're opened with the rest.
+        let controller_subsystem: sdl2::GameControllerSubsystem = sdl_content.game_controller()?;
+
+        Ok(Self { canvas, event_pump, audio, controller_subsystem, controllers: Vec::new(), palette: DEFAULT_PALETTE, video_width: 64 * scale as usize, persistence: None })
+    }
+
+    // Draws the display array to the canvas. Pixels are scaled up by a whole number to fill as much of the window
+    // as they can, with black bars around the display when the window isn't 2:1.
+    fn render(&mut self, emulator: &Emulator) -> Result<(), String> {
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+
+        let scale: usize = self.scale(emulator);
+        let (left, top) = self.origin(emulator);
+
+        self.canvas.set_draw_color(colour(&self.palette, 0)); // Background
+        self.canvas.fill_rect(Rect::new(left, top, (emulator.width() * scale) as u32, (emulator.height() * scale) as u32))?;
+
+        // Until the next frame catches up with a change of resolution the display is drawn as it is.
+        let persistence: Option<&Persistence> = self
+            .persistence
+            .as_ref()
+            .filter(|persistence| persistence.resolution() == (emulator.width(), emulator.height()));
+
+        for col in 0..emulator.width() {
+            for row in 0..emulator.height() {
+                let rgb: [u8; 3] = match persistence {
+                    Some(persistence) => persistence.colour(col, row),
+                    None => self.palette[emulator.display[row][col] as usize & 0x0F],
+                };
+
+                if rgb != self.palette[0] {
+                    let rect = Rect::new(
+                        left + (col * scale) as i32,
+                        top + (row * scale) as i32,
+                        scale as u32,
+                        scale as u32);
+                    self.canvas.set_draw_color(Color::RGB(rgb[0], rgb[1], rgb[2]));
+                    self.canvas.fill_rect(rect)?;
+                }
+            }
+        }
+
+        self.canvas.present();
+
+        Ok(())
+    }
+
+    // Moves the fading pixels on by one 60Hz frame, render only draws where they've got to.
+    fn advance_persistence(&mut self, emulator: &Emulator) {
+        if let Some(persistence) = self.persistence.as_mut() {
+            persistence.update(emulator, &self.palette);
+        }
+    }
+
+    // Whether the window needs drawing again even if the display hasn't changed, because pixels are fading out.
+    fn fading(&self) -> bool {
+        self.persistence.as_ref().is_some_and(Persistence::is_fading)
+    }
+
+    // Size of one CHIP-8 pixel in the window, the biggest whole number that fits the current resolution.
+    fn scale(&self, emulator: &Emulator) -> usize {
+        let (width, height) = self.canvas.output_size().unwrap_or((640, 320));
+
+        (width as usize / emulator.width()).min(height as usize / emulator.height()).max(1)
+    }
+
+    // Top left corner of the display, so it sits in the middle of the window.
+    fn origin(&self, emulator: &Emulator) -> (i32, i32) {
+        let (width, height) = self.canvas.output_size().unwrap_or((640, 320));
+        let scale: usize = self.scale(emulator);
+
+        ((width as i32 - (emulator.width() * scale) as i32) / 2, (height as i32 - (emulator.height() * scale) as i32) / 2)
+    }
+
+    // Switches between a window and the whole screen.
+    fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        let window: &mut Window = self.canvas.window_mut();
+        let fullscreen: bool = window.fullscreen_state() == FullscreenType::Off;
+
+        window.set_fullscreen(if fullscreen { FullscreenType::Desktop } else { FullscreenType::Off })
+    }
+
+    // Saves what's on screen next to the ROM at the window's scale, as a PBM instead of a PNG if asked.
+    fn screenshot(&self, emulator: &Emulator, rom: &Path, pbm: bool) -> Result<PathBuf, EmulatorError> {
+        let extension: &str = if pbm { "pbm" } else { "png" };
+        let path: PathBuf = free_path(rom, extension);
+        let file: File = File::create(&path)?;
+
+        if pbm {
+            write_pbm(emulator, self.scale(emulator), file)?;
+        } else {
+            write_png(emulator, &self.palette, self.scale(emulator), file)?;
+        }
+
+        Ok(path)
+    }
+
+    // Starts recording the screen at the size --scale asked for.
+    fn record_video(&self, emulator: &Emulator, path: &Path, frame: u64) -> Result<VideoRecorder<BufWriter<File>>, EmulatorError> {
+        let (width, _) = emulator.platform.resolution();
+        let scale: usize = (self.video_width / width).max(1);
+
+        VideoRecorder::create(path, emulator, &self.palette, scale, frame)
+    }
+
+    fn open_controller(&mut self, index: u32) {
+        match self.controller_subsystem.open(index) {
+            Ok(controller) => {
+                println!("Controller connected: {}", controller.name());
+                self.controllers.push(controller);
+            }
+            Err(error) => eprintln!("Couldn't open controller: {error}"),
+        }
+    }
+
+    fn close_controller(&mut self, instance_id: u32) {
+        self.controllers.retain(|controller| controller.instance_id() != instance_id);
+    }
+
+    // Keeps the buzzer in step with the sound timer, running is false when the emulator is paused or halted.
+    fn play_sound(&mut self, emulator: &Emulator, running: bool) {
+        if let Some(device) = self.audio.as_mut() {
+            device.lock().0.update(emulator, running);
+        }
+    }
+
+    // Returns whether the sound is now muted.
+    fn toggle_mute(&mut self) -> bool {
+        match self.audio.as_mut() {
+            Some(device) => {
+                let mut buzzer = device.lock();
+                buzzer.0.muted = !buzzer.0.muted;
+                buzzer.0.muted
+            }
+            None => true,
+        }
+    }
+
+    // Puts the error in the title bar so it's visible without a terminal.
+    fn show_error(&mut self, error: &EmulatorError) {
+        // Only fails if the title contains a nul byte, which an error message won't.
+        let _ = self.canvas.window_mut().set_title(&format!("Chip-8 Interpreter - {error}"));
+    }
+
+    // Keeps the registers in the title bar up to date while the debugger is on.
+    fn show_status(&mut self, emulator: &Emulator) {
+        let _ = self.canvas.window_mut().set_title(&format!("Chip-8 Interpreter - {}", status(emulator)));
+    }
+}
+
+fn colour(palette: &Palette, pixel: usize) -> Color {
+    let [r, g, b] = palette[pixel & 0x0F];
+    Color::RGB(r, g, b)
+}
+
+// First of roms/name-1.ext, roms/name-2.ext and so on that doesn't exist yet.
+fn free_path(rom: &Path, extension: &str) -> PathBuf {
+    let stem: String = rom.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+
+    (1..)
+        .map(|n| rom.with_file_name(format!("{stem}-{n}.{extension}")))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+// Save state slots, F1 to F4 load and holding shift saves.
+fn map_state_slot(key: Keycode) -> Option<u8> {
     match key {
-        Keycode::NUM_1 => Some(0x1),
-        Keycode::NUM_2 => Some(0x2),
-        Keycode::NUM_3 => Some(0x3),
-        Keycode::NUM_4 => Some(0xC),
-        Keycode::Q => Some(0x4),
-        Keycode::W => Some(0x5),
-        Keycode::E => Some(0x6),
-        Keycode::R => Some(0xD),
-        Keycode::A => Some(0x7),
-        Keycode::S => Some(0x8),
-        Keycode::D => Some(0x9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0x0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
+        Keycode::F1 => Some(1),
+        Keycode::F2 => Some(2),
+        Keycode::F3 => Some(3),
+        Keycode::F4 => Some(4),
         _ => None,
     }
 }
 
+// Save states sit next to the ROM, e.g. roms/caveexplorer.state1.
+fn state_path(rom: &Path, slot: u8) -> PathBuf {
+    rom.with_extension(format!("state{slot}"))
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
-    // SDL2 variables for setting up the window and canvas.
-    let sdl_content: sdl2::Sdl = sdl2::init().unwrap();
-    let video_subsystem: sdl2::VideoSubsystem = sdl_content.video().unwrap();
+    let args = Args::parse();
 
-    // Resolution is set to 640 x 320, which is 10x the original resolution for visibility.
-    let window: Window = video_subsystem
-                .window("Chip-8 Interpreter", 640, 320)
-                .position_centered()
-                .build()
-                .unwrap();
+    match args.command {
+        Some(Command::Disasm { ref rom, platform }) => {
+            let data: Vec<u8> = std::fs::read(rom)?;
+            print!("{}", disassemble(&data, platform));
+            Ok(())
+        }
+        Some(Command::Asm { ref source, ref output }) => {
+            let rom: Vec<u8> = assemble_file(source)?;
+            let output: PathBuf = output.clone().unwrap_or_else(|| source.with_extension("ch8"));
+            std::fs::write(&output, &rom)?;
+            println!("Wrote {} bytes to {}", rom.len(), output.display());
+            Ok(())
+        }
+        None => run(&args),
+    }
+}
 
-    // Define the canvas which will be drawn to within the opcode functions.
-    let canvas: Canvas<Window> = window.into_canvas().build().unwrap();
+// Reads debugger commands from the terminal on another thread, so the window keeps drawing while it waits.
+fn read_commands() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
 
-    let mut event_pump: sdl2::EventPump = sdl_content.event_pump().unwrap();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() { break }
+        }
+    });
 
-    let args = Args::parse();
+    receiver
+}
+
+// Runs the ROM in an SDL2 window.
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error + 'static>> {
+    // Save states go next to the ROM, or next to the movie when one is played without --rom.
+    let rom: &Path = args.rom.as_deref().map(Path::new).or(args.play.as_deref()).ok_or("a ROM is required, see --help")?;
+
+    let mut frontend: Frontend = Frontend::new(args.scale, args.fullscreen, args.frequency, args.volume as f32 / 100.0, args.waveform, args.mute)?;
+
+    // The ROM is read once, both to look it up and to load. A movie has its own copy in its start state.
+    let data: Option<Vec<u8>> = if args.play.is_none() { Some(std::fs::read(rom)?) } else { None };
 
-    // initialize the emulator struct with pass the ROM path and canvas.
-    let mut emulator: Emulator = load(&args.rom, canvas);
+    // Known ROMs come with settings from the database, anything given on the command line still wins.
+    // A movie has everything it needs already, so the database isn't used for one.
+    let settings: RomSettings = match &data {
+        Some(data) => {
+            let database: Database = match &args.database {
+                Some(dir) => Database::load(dir)?,
+                None => Database::embedded()?,
+            };
+            let settings: RomSettings = database.lookup(data).unwrap_or_default();
+            if !settings.title.is_empty() {
+                println!("Recognised {}", settings.title);
+            }
+            settings
+        }
+        None => RomSettings::default(),
+    };
+
+    let platform: Platform = args.platform.or(settings.platform).unwrap_or_default();
+
+    // Built in themes, then any from --themes, which replace built in ones with the same name.
+    let mut themes: Vec<(String, Palette)> = THEMES.iter().map(|(name, palette)| (name.to_string(), *palette)).collect();
+    if let Some(path) = &args.themes {
+        add_themes(&mut themes, load_themes(path)?);
+    }
+    let mut theme: usize = themes
+        .iter()
+        .position(|(name, _)| name.eq_ignore_ascii_case(&args.theme))
+        .ok_or_else(|| format!("unknown theme '{}'", args.theme))?;
+
+    // Colours from the database and --colours go on top of the chosen theme, as a theme of their own to start on.
+    let colours: Vec<[u8; 3]> = parse_colours(args.colours.as_deref().unwrap_or_default())?;
+    if !settings.colours.is_empty() || !colours.is_empty() {
+        let palette: Palette = overlay(settings.palette(themes[theme].1), &colours);
+        themes.insert(0, ("custom".to_string(), palette));
+        theme = 0;
+    }
+    frontend.palette = themes[theme].1;
+
+    if args.persistence > 0 {
+        frontend.persistence = Some(Persistence::new(args.persistence as f32 / 100.0));
+    }
+
+    // Each platform has its own idea of how fast it should run, some ROMs need something else.
+    let cycles_per_frame: u32 = settings.instructions_per_frame.unwrap_or(platform.instructions_per_frame());
+    let mut ips: u32 = args.ips.unwrap_or(cycles_per_frame * TIMER_HZ);
+
+    // A movie being played starts the emulator exactly as it was recorded, ROM, platform, quirks and all.
+    let mut playing: Option<Movie> = None;
+    let mut emulator: Emulator;
+
+    if let Some(path) = &args.play {
+        let movie: Movie = Movie::load(&mut File::open(path)?)?;
+        emulator = movie.emulator()?;
+        ips = movie.cycles_per_frame * TIMER_HZ;
+        playing = Some(movie);
+    } else {
+        // initialize the emulator struct with the ROM path and platform, then apply any quirk overrides.
+        emulator = load_rom(data.as_deref().unwrap_or_default(), platform)?;
+
+        // The database's quirks are for the platform it picked, so they only apply when that's the one being used.
+        if let Some(quirks) = settings.quirks.filter(|_| settings.platform == Some(platform)) {
+            emulator.quirks = quirks;
+        }
+        if let Some(quirks) = args.quirks {
+            emulator.quirks = quirks;
+        }
+        for setting in &args.quirk {
+            emulator.quirks.apply(setting)?;
+        }
+    }
+
+    if let Some(seed) = args.seed {
+        emulator.seed_rng(seed);
+    }
+
+    if args.vip_random {
+        emulator.rng = Box::new(VipRandom::from_seed(args.seed.unwrap_or(0)));
+    }
+
+    // Recording starts from here, with a fresh seed unless one was given.
+    let mut recording: Option<Movie> = None;
+    if args.record.is_some() {
+        let seed: u64 = args.seed.unwrap_or_else(rand::random);
+
+        // Movies run a whole number of instructions every frame.
+        let cycles_per_frame: u32 = (ips / TIMER_HZ).max(1);
+        if cycles_per_frame * TIMER_HZ != ips {
+            ips = cycles_per_frame * TIMER_HZ;
+            println!("Recording at {ips} instructions per second, movies need a multiple of {TIMER_HZ}");
+        }
+
+        recording = Some(Movie::new(&mut emulator, seed, cycles_per_frame));
+    }
+
+    // How far into the movie playback is.
+    let mut movie_frame: usize = 0;
 
     // Used for tracking cycles. Not really needed past the first few test ROMs.
     let mut _cycles: i32 = 0;
 
-    // Lays out the table for opcodes that's printed in the background.
-    //println!("┏━━━━━━┯━━━━━━━━━━━┯━━━━━━━━━━━┓");
-    //println!("┃Opcode│Instruction│Data       ┃");
-    //println!("┠──────┼───────────┼───────────┨ ");
+    // Lays out the table for opcodes that's printed in the background when tracing.
+    if args.trace {
+        println!("┏━━━━━━┯━━━━━━━━━━━┯━━━━━━━━━━━━┓");
+        println!("┃Opcode│Instruction│Data        ┃");
+        println!("┠──────┼───────────┼────────────┨");
+    }
 
-    // 60fps.
-    let frame_duration: Duration = Duration::from_secs_f32(1.0 / 60.0);
+    // Turns real time into frames and instructions.
+    let mut clock: Clock = Clock::new(ips);
+    let starting_ips: u32 = clock.ips;
+    let mut last_time: Instant = Instant::now();
 
-    /*
-     Target is 540Hz i.e. 540 instructions per second (At least for now.).
-     So cycles per frame is 540 / 60 = 9 cycles per frame.
-    */
+    // Set when the ROM does something invalid, the window stays open with the last frame so it can be inspected.
+    let mut halted: bool = false;
 
-    let cycles_per_frame: i32 = 1000 / 60;
+    // The debugger is used whenever there's something for it to do, --break on its own runs until the first breakpoint.
+    let mut debugger: Option<(Debugger, Receiver<String>)> = None;
+
+    if args.debug || !args.breakpoints.is_empty() {
+        let mut inner: Debugger = Debugger::new(args.debug);
+        inner.breakpoints.extend(&args.breakpoints);
+
+        if args.debug {
+            println!("Paused, type h for help");
+            println!("{}", registers(&emulator));
+        }
+
+        debugger = Some((inner, read_commands()));
+    }
+
+    // Key bindings for this ROM, from the config file if there is one.
+    let rom_name: String = rom.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let config: KeyConfig = match &args.keys {
+        Some(path) => KeyConfig::load(path)?,
+        None => KeyConfig::default(),
+    };
+    let mut keymap: KeyMap = config.keymap(&rom_name, args.layout.as_deref())?;
+
+    // The database's controller buttons are only used if the config doesn't set any of its own.
+    let configured_buttons: bool = !config.buttons.is_empty() || config.roms.get(&rom_name).is_some_and(|rom| !rom.buttons.is_empty());
+    if !configured_buttons {
+        for (button, &key) in &settings.buttons {
+            keymap.bind_button(button, key);
+        }
+    }
+
+    // Counts every 60Hz frame, for timing video frames.
+    let mut frame_count: u64 = 0;
+
+    let mut video: Option<VideoRecorder<BufWriter<File>>> = None;
+    if let Some(path) = &args.record_video {
+        video = Some(frontend.record_video(&emulator, path, frame_count)?);
+    }
+
+    // Every frame is recorded so holding backspace can run the game backwards, up to 30 seconds worth.
+    let mut rewind: Rewind = Rewind::new(30 * 60);
+    let mut rewinding: bool = false;
+
+    // Set when the window needs drawing again without the display having changed, e.g. after a resize.
+    let mut redraw: bool = false;
+
+    // D-pad buttons the left stick is pushed towards, so keys only change when the stick crosses halfway
+    // and the D-pad itself isn't released by the stick wobbling.
+    let mut stick: Vec<&str> = Vec::new();
 
     // Main loop, labeled for breaking on ESC.
     'running: loop {
-        let frame_start = Instant::now();
-        emulator.memory[0x1FF] = 1;
-
-        // Even pump for checking keypresses.
-        if let Some(event) =  event_pump.poll_event() {
+        // Even pump for checking keypresses, everything since the last frame is handled as controller sticks send lots.
+        let events: Vec<Event> = frontend.event_pump.poll_iter().collect();
+        for event in events {
             match event {
                 Event::Quit { .. } |
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
                     break 'running;
                 },
+                // Going back in time would break a movie.
+                Event::KeyDown { keycode: Some(Keycode::Backspace), .. } => {
+                    rewinding = recording.is_none() && playing.is_none();
+                },
+                Event::KeyUp { keycode: Some(Keycode::Backspace), .. } => {
+                    rewinding = false;
+                },
+                Event::KeyDown { keycode: Some(Keycode::M), .. } => {
+                    let muted: bool = frontend.toggle_mute();
+                    println!("Sound {}", if muted { "off" } else { "on" });
+                },
+                Event::KeyDown { keycode: Some(Keycode::Return), keymod, .. } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    if let Err(error) = frontend.toggle_fullscreen() {
+                        eprintln!("Couldn't switch fullscreen: {error}");
+                    }
+                },
+                // The display is scaled to the window's new size.
+                Event::Window { win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed, .. } => {
+                    redraw = true;
+                },
+                Event::KeyDown { keycode: Some(Keycode::F5), .. } => {
+                    theme = (theme + 1) % themes.len();
+                    frontend.palette = themes[theme].1;
+                    redraw = true;
+                    println!("Theme {}", themes[theme].0);
+                },
+                // Changing speed would break a movie, which runs a set number of instructions every frame.
+                Event::KeyDown { keycode: Some(key @ (Keycode::F6 | Keycode::F7 | Keycode::F8)), .. } => {
+                    if recording.is_some() || playing.is_some() {
+                        eprintln!("The speed can't be changed while a movie is recording or playing");
+                    } else {
+                        match key {
+                            Keycode::F6 => clock.ips = starting_ips,
+                            Keycode::F7 => clock.scale_speed(1.0 / 1.25),
+                            _ => clock.scale_speed(1.25),
+                        }
+                        println!("Speed {} instructions per second", clock.ips);
+                    }
+                },
+                Event::KeyDown { keycode: Some(Keycode::F11), .. } => {
+                    if let Some(recorder) = video.take() {
+                        match recorder.finish(frame_count) {
+                            Ok(()) => println!("Stopped recording video"),
+                            Err(error) => eprintln!("Couldn't finish the video: {error}"),
+                        }
+                    } else {
+                        let path: PathBuf = free_path(rom, "gif");
+
+                        match frontend.record_video(&emulator, &path, frame_count) {
+                            Ok(recorder) => {
+                                video = Some(recorder);
+                                println!("Recording video to {}", path.display());
+                            }
+                            Err(error) => eprintln!("Couldn't start recording video: {error}"),
+                        }
+                    }
+                },
+                Event::KeyDown { keycode: Some(Keycode::F12), keymod, .. } => {
+                    let pbm: bool = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+
+                    match frontend.screenshot(&emulator, rom, pbm) {
+                        Ok(path) => println!("Saved screenshot to {}", path.display()),
+                        Err(error) => eprintln!("Couldn't save screenshot: {error}"),
+                    }
+                },
+                Event::KeyDown { keycode: Some(key), keymod, .. } if map_state_slot(key).is_some() => {
+                    let slot: u8 = map_state_slot(key).unwrap();
+                    let path: PathBuf = state_path(rom, slot);
+
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        match File::create(&path).map_err(EmulatorError::from).and_then(|mut file| emulator.save_state(&mut file)) {
+                            Ok(()) => println!("Saved slot {slot} to {}", path.display()),
+                            Err(error) => eprintln!("Couldn't save slot {slot}: {error}"),
+                        }
+                    } else if recording.is_some() || playing.is_some() {
+                        eprintln!("Save states can't be loaded while a movie is recording or playing");
+                    } else {
+                        match File::open(&path).map_err(EmulatorError::from).and_then(|mut file| Emulator::load_state(&mut file)) {
+                            // The random numbers carry on from where they were, so --seed and --vip-random still hold.
+                            Ok(mut loaded) => {
+                                std::mem::swap(&mut loaded.rng, &mut emulator.rng);
+                                emulator = loaded;
+                                halted = false;
+                                println!("Loaded slot {slot} from {}", path.display());
+                            }
+                            Err(error) => eprintln!("Couldn't load slot {slot}: {error}"),
+                        }
+                    }
+                },
                 Event::KeyDown { keycode: Some(key), .. } => {
-                    if let Some(index) = map_keys(key) {
+                    if let Some(index) = keymap.chip8_key(&key.name()) {
                         emulator.keypad[index as usize] = true;
                     }
                 },
                 Event::KeyUp { keycode: Some(key), .. } => {
-                    if let Some(index) = map_keys(key) {
+                    if let Some(index) = keymap.chip8_key(&key.name()) {
                         emulator.keypad[index as usize] = false;
                     }
                 },
+                Event::ControllerDeviceAdded { which, .. } => {
+                    frontend.open_controller(which);
+                },
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    frontend.close_controller(which);
+                },
+                Event::ControllerButtonDown { button, .. } => {
+                    if let Some(index) = keymap.chip8_button(&button.string()) {
+                        emulator.keypad[index as usize] = true;
+                    }
+                },
+                Event::ControllerButtonUp { button, .. } => {
+                    if let Some(index) = keymap.chip8_button(&button.string()) {
+                        emulator.keypad[index as usize] = false;
+                    }
+                },
+                // The left stick works like the D-pad once it's pushed halfway.
+                Event::ControllerAxisMotion { axis: axis @ (Axis::LeftX | Axis::LeftY), value, .. } => {
+                    let (negative, positive) = if axis == Axis::LeftX { ("dpleft", "dpright") } else { ("dpup", "dpdown") };
+
+                    for (button, pressed) in [(negative, value < -16384), (positive, value > 16384)] {
+                        if stick.contains(&button) == pressed { continue }
+
+                        if pressed { stick.push(button) } else { stick.retain(|&held| held != button) }
+
+                        if let Some(index) = keymap.chip8_button(button) {
+                            emulator.keypad[index as usize] = pressed;
+                        }
+                    }
+                },
                 _ => {}
             }
         }
 
-        // Complete target cycles per frame.
-        let mut i: i32 = 0;
-        while i < cycles_per_frame {
-            // Fetch the instruction, and pass it to the decode function along with the emulator.
-            let instruction: u16 = fetch(&mut emulator);
-            decode(&mut emulator, instruction);
-
-            i += 1
+        // Run any debugger commands typed since the last frame.
+        if let Some((debugger, commands)) = debugger.as_mut() {
+            while let Ok(line) = commands.try_recv() {
+                match parse_command(&line) {
+                    Ok(command) => {
+                        if let Some(output) = debugger.command(command, &emulator) {
+                            println!("{output}");
+                        }
+                    }
+                    Err(error) => println!("{error}"),
+                }
+            }
         }
 
-        if emulator.vram_updated {
-            emulator.canvas.set_draw_color(Color::RGB(0, 0, 0)); // Black background
-            emulator.canvas.clear();
+        // The time since last time round is emulated as 60Hz frames, usually one but more if the host fell behind.
+        let now: Instant = Instant::now();
+        let frames: u32 = clock.advance(now - last_time);
+        last_time = now;
+
+        for _ in 0..frames {
+            // Picks the first test from Timendus' test suite menus. It's set at the start of every frame, like
+            // tests/common does, so a movie sees it at the same point however the host's timing went.
+            emulator.memory[0x1FF] = 1;
 
-            for col in 0..64 {
-                for row in 0..32 {
-                    if emulator.display[row][col] == 1 {
-                        let rect = Rect::new(
-                            (col * 10) as i32,
-                            (row * 10) as i32, 
-                            10,
-                            10);
-                        emulator.canvas.set_draw_color(Color::RGB(255, 179, 71));
-                        emulator.canvas.fill_rect(rect).unwrap();
+            // Movies run on frames rather than time, so the keypad is swapped out or recorded before every frame that runs.
+            if !halted && !rewinding {
+                if let Some(movie) = &playing {
+                    match movie.frames.get(movie_frame) {
+                        Some(&keypad) => emulator.keypad = keypad,
+                        None => {
+                            println!("Movie finished after {movie_frame} frames, the keyboard has control");
+                            emulator.keypad = [false; 16];
+                            playing = None;
+                        }
                     }
+                    movie_frame += 1;
+                }
+
+                if let Some(movie) = recording.as_mut() {
+                    movie.record(emulator.keypad);
                 }
             }
 
-            emulator.canvas.present();
-            emulator.vram_updated = false;
-        }
+            // Steps back one frame, keeping the keys that are actually held down now and the random numbers.
+            if rewinding {
+                if let Some(mut previous) = rewind.pop() {
+                    let keypad: [bool; 16] = emulator.keypad;
+                    std::mem::swap(&mut previous.rng, &mut emulator.rng);
+                    emulator = previous;
+                    emulator.keypad = keypad;
+                    halted = false;
+                }
+            }
+
+            // Run this frame's share of instructions, or until a draw waits for the next frame.
+            emulator.waiting_for_vblank = false;
+            let instructions: u32 = clock.instructions_for_frame();
+            let mut i: u32 = 0;
+            while i < instructions && !emulator.waiting_for_vblank && !emulator.exited && !halted && !rewinding {
+                if let Some((debugger, _)) = debugger.as_mut() {
+                    if debugger.is_paused() { break }
+
+                    if !debugger.before_step(&emulator) {
+                        println!("Breakpoint at {:03X}", emulator.pc);
+                        println!("{}", registers(&emulator));
+                        break;
+                    }
+                }
+
+                // Fetch the opcode, decode it, then execute the instruction on the emulator.
+                let result = fetch(&mut emulator).and_then(|opcode| {
+                    let instruction: Instruction = decode(opcode);
+
+                    if args.trace {
+                        println!("┃ {opcode:04X} │ {:<9} │ {:<10} ┃", instruction.mnemonic(), instruction.operands());
+                    }
+
+                    execute(&mut emulator, instruction)
+                });
+
+                if let Err(error) = result {
+                    eprintln!("Emulation stopped: {error}");
+                    frontend.show_error(&error);
+                    halted = true;
+                }
+
+                i += 1;
+
+                if let Some((debugger, _)) = debugger.as_mut() {
+                    if debugger.after_step(&emulator) {
+                        println!("{}", registers(&emulator));
+                        break;
+                    }
+                }
+            }
+
+            // Frames where nothing ran don't need rewinding through.
+            if i > 0 {
+                rewind.push(&emulator);
+            }
+
+            // The display is only drawn once all the frames due have run, but every change goes into the video.
+            if emulator.vram_updated {
+                if let Some(recorder) = video.as_mut() {
+                    if let Err(error) = recorder.frame(&emulator, frame_count) {
+                        eprintln!("Stopped recording video: {error}");
+                        video = None;
+                    }
+                }
+
+                emulator.vram_updated = false;
+                redraw = true;
+            }
+
+            // Pixels fade at 60Hz like everything else, however many frames are drawn at once or redrawn.
+            frontend.advance_persistence(&emulator);
+
+            // 0x00FD asks the interpreter to quit.
+            if emulator.exited { break 'running }
+
+            // Again, not really needed outside of the first few test ROMs.
+            //cycles += 1;
 
-        // Again, not really needed outside of the first few test ROMs.
-        //cycles += 1;
+            /*if cycles == 20 {
+                break;
+            }*/
 
-        /*if cycles == 20 {
-            break;
-        }*/
+            // Time stands still while the debugger is paused.
+            let paused: bool = debugger.as_ref().is_some_and(|(debugger, _)| debugger.is_paused());
 
-        if emulator.delay != 0 { emulator.delay -= 1 }
-        if emulator.sound != 0 {
-            emulator.sound -= 1;
-            println!("BEEP!")
+            // One tick per frame, so the timers count down at 60Hz whatever the speed.
+            if !halted && !paused && !rewinding {
+                tick_timers(&mut emulator);
+            }
+
+            frame_count += 1;
         }
 
-        let elapsed_time = frame_start.elapsed();
-        if elapsed_time < frame_duration {
-            thread::sleep(frame_duration - elapsed_time);
+        // Fading pixels change every frame on their own.
+        if redraw || (frames > 0 && frontend.fading()) {
+            frontend.render(&emulator)?;
+            redraw = false;
         }
+
+        let paused: bool = debugger.as_ref().is_some_and(|(debugger, _)| debugger.is_paused());
+        frontend.play_sound(&emulator, !halted && !paused && !rewinding);
+
+        // A halted ROM's error stays in the title instead.
+        if debugger.is_some() && !halted {
+            frontend.show_status(&emulator);
+        }
+
+        thread::sleep(clock.until_next_frame());
+    }
+
+    if let Some(recorder) = video {
+        recorder.finish(frame_count)?;
     }
 
-    //println!("┗━━━━━━┷━━━━━━━━━━━┷━━━━━━━━━━━┛");
+    if let (Some(movie), Some(path)) = (&recording, &args.record) {
+        movie.save(&mut File::create(path)?)?;
+        println!("Recorded {} frames to {}", movie.frames.len(), path.display());
+    }
+
+    if args.trace {
+        println!("┗━━━━━━┷━━━━━━━━━━━┷━━━━━━━━━━━━┛");
+    }
 
     Ok(())
 }
@@ -0,0 +1,191 @@
+use crate::error::EmulatorError;
+use crate::system::Emulator;
+
+use std::io::{Read, Write};
+
+// Every movie starts with this, followed by the format version.
+const MAGIC: &[u8; 4] = b"C8MV";
+
+// Bumped whenever the layout changes.
+pub const MOVIE_VERSION: u8 = 1;
+
+// A recording of a run: the emulator as it started, the random seed and the keys held on every frame.
+// Playing the keys back into the same start, with the same seed and speed, gives exactly the same run.
+pub struct Movie {
+    pub seed: u64, // Seed for the 0xCxkk random numbers.
+    pub cycles_per_frame: u32, // Instructions run each frame while recording.
+    pub start: Vec<u8>, // Save state of the emulator before the first frame.
+    pub frames: Vec<[bool; 16]>, // Keypad on each frame.
+}
+
+impl Movie {
+    // Starts a recording, seeding the emulator so its random numbers can be played back.
+    pub fn new(emulator: &mut Emulator, seed: u64, cycles_per_frame: u32) -> Self {
+        emulator.seed_rng(seed);
+
+        let mut start: Vec<u8> = Vec::new();
+        emulator.save_state(&mut start).expect("writing to a Vec can't fail");
+
+        Self { seed, cycles_per_frame, start, frames: Vec::new() }
+    }
+
+    // Adds the keys held for the next frame.
+    pub fn record(&mut self, keypad: [bool; 16]) {
+        self.frames.push(keypad);
+    }
+
+    // The emulator in the state the recording started from, ready to have the frames played into it.
+    pub fn emulator(&self) -> Result<Emulator, EmulatorError> {
+        let mut emulator: Emulator = Emulator::load_state(&mut self.start.as_slice())?;
+        emulator.seed_rng(self.seed);
+
+        Ok(emulator)
+    }
+
+    pub fn save(&self, writer: &mut impl Write) -> Result<(), EmulatorError> {
+        let mut data: Vec<u8> = Vec::with_capacity(self.start.len() + self.frames.len() * 2 + 32);
+
+        data.extend_from_slice(MAGIC);
+        data.push(MOVIE_VERSION);
+        data.extend_from_slice(&self.seed.to_be_bytes());
+        data.extend_from_slice(&self.cycles_per_frame.to_be_bytes());
+        data.extend_from_slice(&(self.start.len() as u32).to_be_bytes());
+        data.extend_from_slice(&self.start);
+
+        // Each frame is a 16 bit mask, bit n is key n.
+        for keypad in &self.frames {
+            let mask: u16 = keypad.iter().enumerate().fold(0, |mask, (key, &down)| mask | (down as u16) << key);
+            data.extend_from_slice(&mask.to_be_bytes());
+        }
+
+        writer.write_all(&data)?;
+
+        Ok(())
+    }
+
+    pub fn load(reader: &mut impl Read) -> Result<Movie, EmulatorError> {
+        let mut data: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        if data.len() < 21 || &data[0..4] != MAGIC {
+            return Err(bad_movie("not a movie file"));
+        }
+        if data[4] != MOVIE_VERSION {
+            return Err(bad_movie(&format!("version {} isn't supported, expected {MOVIE_VERSION}", data[4])));
+        }
+
+        let seed: u64 = u64::from_be_bytes(data[5..13].try_into().unwrap());
+        let cycles_per_frame: u32 = u32::from_be_bytes(data[13..17].try_into().unwrap());
+        let start_length: usize = u32::from_be_bytes(data[17..21].try_into().unwrap()) as usize;
+
+        let start: &[u8] = data.get(21..21 + start_length).ok_or_else(|| bad_movie("file is truncated"))?;
+        let inputs: &[u8] = &data[21 + start_length..];
+
+        if !inputs.len().is_multiple_of(2) {
+            return Err(bad_movie("file is truncated"));
+        }
+
+        let frames: Vec<[bool; 16]> = inputs
+            .chunks(2)
+            .map(|word| {
+                let mask: u16 = u16::from_be_bytes([word[0], word[1]]);
+                std::array::from_fn(|key| mask & (1 << key) != 0)
+            })
+            .collect();
+
+        Ok(Movie { seed, cycles_per_frame, start: start.to_vec(), frames })
+    }
+}
+
+fn bad_movie(reason: &str) -> EmulatorError {
+    EmulatorError::BadMovie { reason: reason.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Platform;
+    use crate::system::step;
+
+    // Stores a random number in V0 whenever key 0 is held, round and round forever.
+    fn recorded_movie() -> (Movie, Vec<u8>) {
+        let mut emulator: Emulator = Emulator::new(Platform::Vip);
+        emulator.memory[0x200..0x208].copy_from_slice(&[0xE5, 0xA1, 0xC0, 0xFF, 0x12, 0x00, 0x00, 0x00]);
+
+        let mut movie: Movie = Movie::new(&mut emulator, 1234, 3);
+        let mut randoms: Vec<u8> = Vec::new();
+
+        for frame in 0..20 {
+            let keypad: [bool; 16] = std::array::from_fn(|key| key == 0 && frame % 3 != 0 || key == 15 && frame == 7);
+            movie.record(keypad);
+            emulator.keypad = keypad;
+
+            for _ in 0..movie.cycles_per_frame {
+                step(&mut emulator).unwrap();
+            }
+            randoms.push(emulator.vx[0]);
+        }
+
+        (movie, randoms)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (movie, _) = recorded_movie();
+
+        let mut data: Vec<u8> = Vec::new();
+        movie.save(&mut data).unwrap();
+        let loaded: Movie = Movie::load(&mut data.as_slice()).unwrap();
+
+        assert_eq!(loaded.seed, 1234);
+        assert_eq!(loaded.cycles_per_frame, 3);
+        assert_eq!(loaded.start, movie.start);
+        assert_eq!(loaded.frames, movie.frames);
+    }
+
+    #[test]
+    fn playback_repeats_the_run() {
+        let (movie, randoms) = recorded_movie();
+        let mut emulator: Emulator = movie.emulator().unwrap();
+
+        assert!(randoms.windows(2).any(|pair| pair[0] != pair[1]));
+
+        for (keypad, &expected) in movie.frames.iter().zip(&randoms) {
+            emulator.keypad = *keypad;
+
+            for _ in 0..movie.cycles_per_frame {
+                step(&mut emulator).unwrap();
+            }
+            assert_eq!(emulator.vx[0], expected);
+        }
+    }
+
+    #[test]
+    fn bad_movies_are_rejected() {
+        let (movie, _) = recorded_movie();
+        let mut data: Vec<u8> = Vec::new();
+        movie.save(&mut data).unwrap();
+
+        let mut wrong_version: Vec<u8> = data.clone();
+        wrong_version[4] = MOVIE_VERSION + 1;
+
+        let mut wrong_start: Vec<u8> = data.clone();
+        wrong_start[17..21].copy_from_slice(&u32::MAX.to_be_bytes());
+
+        let cases: [(&[u8], &str); 5] = [
+            (b"C8SS\x01", "not a movie"),
+            (&wrong_version, "isn't supported"),
+            (&wrong_start, "truncated"),
+            (&data[..data.len() - 1], "truncated"),
+            (&data[..20], "not a movie"),
+        ];
+
+        for (movie, reason) in cases {
+            match Movie::load(&mut &movie[..]) {
+                Err(EmulatorError::BadMovie { reason: error }) => assert!(error.contains(reason), "{error}"),
+                Err(error) => panic!("expected a bad movie, got {error}"),
+                Ok(_) => panic!("expected '{reason}' but the movie loaded"),
+            }
+        }
+    }
+}
@@ -1,174 +1,247 @@
-use crate::system::Emulator;
+use crate::error::EmulatorError;
+use crate::platform::Platform;
+use crate::system::{fetch, Emulator, BIG_FONT_START, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// Skips the next instruction, XO-CHIP's 0xF000 nnnn is four bytes long so it needs skipping twice.
+fn skip_next(emulator: &mut Emulator) {
+    if emulator.platform.supports_xochip() && matches!(fetch(emulator), Ok(0xF000)) {
+        emulator.pc = emulator.pc.wrapping_add(2);
+    }
+
+    emulator.pc = emulator.pc.wrapping_add(2);
+}
 
-use rand::prelude::*;
+// Moves the selected planes of the display by (dx, dy), pixels scrolled in from the edges are blank.
+fn scroll(emulator: &mut Emulator, dx: isize, dy: isize) {
+    let width: usize = emulator.width();
+    let height: usize = emulator.height();
+    let planes: u8 = emulator.planes;
+    let old: [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT] = emulator.display;
 
-// Clears both the canvas and display array.
-pub fn e_0(emulator: &mut Emulator) -> &mut Emulator {
-    //println!("┃ 00E0 │ CLS       │           ┃");
+    for row in 0..height {
+        for col in 0..width {
+            let src_y: isize = row as isize - dy;
+            let src_x: isize = col as isize - dx;
 
+            let moved: u8 = if (0..height as isize).contains(&src_y) && (0..width as isize).contains(&src_x) {
+                old[src_y as usize][src_x as usize] & planes
+            } else {
+                0
+            };
+
+            emulator.display[row][col] = (old[row][col] & !planes) | moved;
+        }
+    }
+
+    emulator.vram_updated = true;
+}
+
+// Clears the selected planes of the display array.
+pub fn e_0(emulator: &mut Emulator) -> Result<(), EmulatorError> {
     for y in emulator.display.iter_mut() {
-        y.fill(0);
+        for pixel in y.iter_mut() {
+            *pixel &= !emulator.planes;
+        }
     }
 
     emulator.vram_updated = true;
 
-    emulator
+    Ok(())
 }
 
 // Returns from subroutine.
-pub fn e_e(emulator: &mut Emulator) -> &mut Emulator {
-    //println!("┃ 00EE │ RET       │           ┃");
+pub fn e_e(emulator: &mut Emulator) -> Result<(), EmulatorError> {
+    if emulator.sp == 0 {
+        return Err(EmulatorError::StackUnderflow { pc: emulator.pc.wrapping_sub(2) });
+    }
 
-    emulator.pc = emulator.stack[emulator.sp as usize];
     emulator.sp -= 1;
+    emulator.pc = emulator.stack[emulator.sp as usize];
 
-    emulator
+    Ok(())
 }
 
-// 0x0nnn is ignored as it isn't needed.
-// Jumps to a location in memory.
-pub fn one_nnn(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let nnn: u16 = opcode & 0x0FFF;
+// Scroll the display down n lines (SUPER-CHIP).
+pub fn c_n(emulator: &mut Emulator, n: u8) -> Result<(), EmulatorError> {
+    scroll(emulator, 0, n as isize);
+
+    Ok(())
+}
+
+// Scroll the display up n lines (XO-CHIP).
+pub fn d_n(emulator: &mut Emulator, n: u8) -> Result<(), EmulatorError> {
+    scroll(emulator, 0, -(n as isize));
+
+    Ok(())
+}
+
+// Scroll the display right 4 pixels (SUPER-CHIP).
+pub fn f_b(emulator: &mut Emulator) -> Result<(), EmulatorError> {
+    scroll(emulator, 4, 0);
+
+    Ok(())
+}
+
+// Scroll the display left 4 pixels (SUPER-CHIP).
+pub fn f_c(emulator: &mut Emulator) -> Result<(), EmulatorError> {
+    scroll(emulator, -4, 0);
+
+    Ok(())
+}
+
+// Exit the interpreter (SUPER-CHIP).
+pub fn f_d(emulator: &mut Emulator) -> Result<(), EmulatorError> {
+    emulator.exited = true;
+    emulator.pc = emulator.pc.wrapping_sub(2); // Stay on this instruction in case the frontend keeps going.
+
+    Ok(())
+}
+
+// Switch to low resolution (SUPER-CHIP).
+pub fn f_e(emulator: &mut Emulator) -> Result<(), EmulatorError> {
+    emulator.hires = false;
+    emulator.vram_updated = true;
+
+    // XO-CHIP clears the whole display when the resolution changes.
+    if emulator.platform.supports_xochip() {
+        emulator.display = [[0; SCREEN_WIDTH]; SCREEN_HEIGHT];
+    }
 
-    //println!("┃ {opcode:04X} │ JP        │ {nnn:03X}       ┃");
+    Ok(())
+}
+
+// Switch to high resolution (SUPER-CHIP).
+pub fn f_f(emulator: &mut Emulator) -> Result<(), EmulatorError> {
+    emulator.hires = true;
+    emulator.vram_updated = true;
+
+    // XO-CHIP clears the whole display when the resolution changes.
+    if emulator.platform.supports_xochip() {
+        emulator.display = [[0; SCREEN_WIDTH]; SCREEN_HEIGHT];
+    }
 
+    Ok(())
+}
+
+// 0x0nnn is ignored as it isn't needed.
+// Jumps to a location in memory.
+pub fn one_nnn(emulator: &mut Emulator, nnn: u16) -> Result<(), EmulatorError> {
     emulator.pc = nnn;
-    emulator
+
+    Ok(())
 }
 
 // Calls a subroutine.
-pub fn two_nnn(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let nnn: u16 = opcode & 0x0FFF;
-    
-    //println!("┃ {opcode:04X} │ CALL      │ {nnn:03X}       ┃");
+pub fn two_nnn(emulator: &mut Emulator, nnn: u16) -> Result<(), EmulatorError> {
+    if emulator.sp as usize >= emulator.stack.len() {
+        return Err(EmulatorError::StackOverflow { pc: emulator.pc.wrapping_sub(2) });
+    }
 
-    emulator.sp += 1;
     emulator.stack[emulator.sp as usize] = emulator.pc;
+    emulator.sp += 1;
     emulator.pc = nnn;
 
-    emulator
+    Ok(())
 }
 
 // Skips next instruction if Vx = kk
-pub fn three_x_kk(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let kk: u16 = opcode & 0x00FF;
-
-    //println!("┃ {opcode:04X} │ SE        │ V{x:01X}, {kk:02X}    ┃");
-
-    if emulator.vx[x] == kk as u8 {
-        emulator.pc += 2
+pub fn three_x_kk(emulator: &mut Emulator, x: usize, kk: u8) -> Result<(), EmulatorError> {
+    if emulator.vx[x] == kk {
+        skip_next(emulator)
     }
 
-    emulator
+    Ok(())
 }
 
 // Skip next instruction if Vx != kk.
-pub fn four_x_kk(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let kk: u16 = opcode & 0x00FF;
+pub fn four_x_kk(emulator: &mut Emulator, x: usize, kk: u8) -> Result<(), EmulatorError> {
+    if emulator.vx[x] != kk {
+        skip_next(emulator)
+    }
 
-    //println!("┃ {opcode:04X} │ SNE       │ V{x:01X}, {kk:02X}    ┃");
+    Ok(())
+}
 
-    if emulator.vx[x] != kk as u8 {
-        emulator.pc += 2
+// Skip next instruction if Vx = Vy.
+pub fn five_x_y_0(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
+    if emulator.vx[x] == emulator.vx[y] {
+        skip_next(emulator)
     }
 
-    emulator
+    Ok(())
 }
 
-// Skip next instruction if Vx = Vy.
-pub fn five_x_y_0(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
+// Store registers Vx through Vy in memory starting at location I, I is left alone (XO-CHIP).
+pub fn five_x_y_2(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
+    // The range can run backwards, e.g. 0x5A32 stores VA, V9 ... V3.
+    for (offset, n) in register_range(x, y).into_iter().enumerate() {
+        emulator.write(emulator.i as usize + offset, emulator.vx[n])?;
+    }
 
-    //println!("┃ {opcode:04X} │ SE        │ V{x:01X}, V{y:01X}    ┃");
+    Ok(())
+}
 
-    if emulator.vx[x] == emulator.vx[y] {
-        emulator.pc += 2
+// Read registers Vx through Vy from memory starting at location I, I is left alone (XO-CHIP).
+pub fn five_x_y_3(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
+    for (offset, n) in register_range(x, y).into_iter().enumerate() {
+        emulator.vx[n] = emulator.read(emulator.i as usize + offset)?;
     }
 
-    emulator
+    Ok(())
 }
 
-// Set Vx = kk
-pub fn six_x_kk(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: u16 = (opcode & 0x0F00) >> 8;
-    let kk: u16 = opcode & 0x00FF;
+// Registers from x to y inclusive, in whichever direction that goes.
+fn register_range(x: usize, y: usize) -> Vec<usize> {
+    if x <= y { (x..=y).collect() } else { (y..=x).rev().collect() }
+}
 
-    //println!("┃ {opcode:04X} │ LD        │ V{x:01X}, {kk:02X}    ┃");
+// Set Vx = kk
+pub fn six_x_kk(emulator: &mut Emulator, x: usize, kk: u8) -> Result<(), EmulatorError> {
+    emulator.vx[x] = kk;
 
-    emulator.vx[x as usize] = kk as u8;
-    emulator
+    Ok(())
 }
 
 // Set Vx = Vx + kk.
-pub fn seven_x_kk(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let kk: u16 = opcode & 0x00FF;
-
-    emulator.vx[x] = emulator.vx[x].overflowing_add(kk as u8).0;
+pub fn seven_x_kk(emulator: &mut Emulator, x: usize, kk: u8) -> Result<(), EmulatorError> {
+    emulator.vx[x] = emulator.vx[x].overflowing_add(kk).0;
 
-    emulator
+    Ok(())
 }
 
 // Set Vx = Vy.
-pub fn eight_x_y_0(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
-
-    //println!("┃ {opcode:04X} │ LD        │ V{x:01X}, V{y:01X}    ┃");
-
+pub fn eight_x_y_0(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
     emulator.vx[x] = emulator.vx[y];
 
-    emulator
+    Ok(())
 }
 
 // Set Vx = Vx OR Vy.
-pub fn eight_x_y_1(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
+pub fn eight_x_y_1(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
+    emulator.vx[x] |= emulator.vx[y];
+    if emulator.quirks.vf_reset { emulator.vx[0xF] = 0 }
 
-    //println!("┃ {opcode:04X} │ OR        │ V{x:01X}, V{y:01X}   ┃");
-
-    emulator.vx[x] = emulator.vx[x] | emulator.vx[y];
-    emulator.vx[0xF] = 0; // Reset VF, required by quicks test.
-
-    emulator
+    Ok(())
 }
 
 // Set Vx = Vx AND Vy.
-pub fn eight_x_y_2(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
-
-    //println!("┃ {opcode:04X} │ AND       │ V{x:01X}, V{y:01X}   ┃");
-
-    emulator.vx[x] = emulator.vx[x] & emulator.vx[y];
-    emulator.vx[0xF] = 0; // Reset VF, required by quicks test.
+pub fn eight_x_y_2(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
+    emulator.vx[x] &= emulator.vx[y];
+    if emulator.quirks.vf_reset { emulator.vx[0xF] = 0 }
 
-    emulator}
+    Ok(())
+}
 
 // Set Vx = Vx XOR Vy.
-pub fn eight_x_y_3(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
-
-    //println!("┃ {opcode:04X} │ XOR       │ V{x:01X}, V{y:01X}   ┃");
-
-    emulator.vx[x] = emulator.vx[x] ^ emulator.vx[y];
-    emulator.vx[0xF] = 0; // Reset VF, required by quicks test.
+pub fn eight_x_y_3(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
+    emulator.vx[x] ^= emulator.vx[y];
+    if emulator.quirks.vf_reset { emulator.vx[0xF] = 0 }
 
-    emulator
+    Ok(())
 }
 
 // Set Vx = Vx + Vy, set VF = carry.
-pub fn eight_x_y_4(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
-
-    //println!("┃ {opcode:04X} │ ADD       │ V{x:01X}, V{y:01X}    ┃");
-
+pub fn eight_x_y_4(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
     let result: (u8, bool) = emulator.vx[x].overflowing_add(emulator.vx[y]);
 
     emulator.vx[x] = result.0;
@@ -179,16 +252,11 @@ pub fn eight_x_y_4(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
         emulator.vx[15] = 0
     }
 
-    emulator
+    Ok(())
 }
 
 // Set Vx = Vx - Vy, set VF = NOT borrow.
-pub fn eight_x_y_5(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
-
-    //println!("┃ {opcode:04X} │ SUB       │ V{x:01X}, V{y:01X}   ┃");
-
+pub fn eight_x_y_5(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
     let result: (u8, bool) = emulator.vx[x].overflowing_sub(emulator.vx[y]);
 
     emulator.vx[x] = result.0;
@@ -199,35 +267,25 @@ pub fn eight_x_y_5(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
         emulator.vx[15] = 0
     }
 
-    emulator
+    Ok(())
 }
 
 // Set Vx = Vx SHR 1.
-pub fn eight_x_y_6(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    
-    // This is for the shifting quirk. Will eventually be configurable.
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
-    emulator.vx[x] = emulator.vx[y];
-
-    //println!("┃ {opcode:04X} │ SHR       │ V{x:01X} {{, V{y:01X}}} ┃");
+pub fn eight_x_y_6(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
+    // Shifting quirk, the original interpreter shifted Vy into Vx.
+    if !emulator.quirks.shifting { emulator.vx[x] = emulator.vx[y] }
 
     let lsb: u8 =  emulator.vx[x] & 0b1;
 
-    emulator.vx[x] = emulator.vx[x] >> 1;
+    emulator.vx[x] >>= 1;
 
     emulator.vx[0xF] = lsb;
 
-    emulator
+    Ok(())
 }
 
 // Set Vx = Vy - Vx, set VF = NOT borrow.
-pub fn eight_x_y_7(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
-
-    //println!("┃ {opcode:04X} │ SUBN      │ V{x:01X}, V{y:01X}   ┃");
-
+pub fn eight_x_y_7(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
     let result: (u8, bool) = emulator.vx[y].overflowing_sub(emulator.vx[x]);
 
     emulator.vx[x] = result.0;
@@ -238,150 +296,188 @@ pub fn eight_x_y_7(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
         emulator.vx[15] = 0
     }
 
-    emulator
+    Ok(())
 }
 
 // Set Vx = Vx SHL 1.
-pub fn eight_x_y_e(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-
-    // This is for the shifting quirk. Will eventually be configurable.
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
-    emulator.vx[x] = emulator.vx[y];
-
-    //println!("┃ {opcode:04X} │ SHL       │ V{x:01X} {{, V{y:01X}}} ┃");
+pub fn eight_x_y_e(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
+    // Shifting quirk, the original interpreter shifted Vy into Vx.
+    if !emulator.quirks.shifting { emulator.vx[x] = emulator.vx[y] }
 
     let msb: u8 = emulator.vx[x] >> 7 & 1;
 
-    emulator.vx[x] = emulator.vx[x] << 1;
+    emulator.vx[x] <<= 1;
 
     emulator.vx[0xF] = msb;
 
-    emulator
+    Ok(())
 }
 
 // Skip next instruction if Vx != Vy.
-pub fn nine_x_y_0(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
-
-    //println!("┃ {opcode:04X} │ SNE       │ V{x:01X}, V{y:01X}    ┃");
-
+pub fn nine_x_y_0(emulator: &mut Emulator, x: usize, y: usize) -> Result<(), EmulatorError> {
     if emulator.vx[x] != emulator.vx[y] {
-        emulator.pc += 2
+        skip_next(emulator)
     }
 
-    emulator
+    Ok(())
 }
 
 // Set I = nnn.
-pub fn a_nnn(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let nnn: u16 = opcode & 0x0FFF;
-
-    //println!("┃ {opcode:04X} │ LD        │ I, {nnn:03X}    ┃");
-
+pub fn a_nnn(emulator: &mut Emulator, nnn: u16) -> Result<(), EmulatorError> {
     emulator.i = nnn;
-    emulator
+
+    Ok(())
 }
 
 // Jump to location nnn + V0.
-pub fn b_nnn(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let nnn: u16 = opcode & 0x0FFF;
+pub fn b_nnn(emulator: &mut Emulator, nnn: u16) -> Result<(), EmulatorError> {
+    // Jumping quirk, CHIP-48 read this as Bxnn and added Vx instead.
+    let x: usize = if emulator.quirks.jumping { (nnn >> 8) as usize } else { 0 };
 
-    emulator.pc = (emulator.vx[0] as u16 )+ nnn;
+    emulator.pc = (emulator.vx[x] as u16) + nnn;
 
-    emulator
+    Ok(())
 }
 
 // Set Vx = random byte AND kk.
-pub fn c_x_kk(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    let kk: u8 = (opcode & 0x00FF) as u8;
-
-    let mut rng: ThreadRng = rand::thread_rng();
-    let y: u8 = rng.gen();
+pub fn c_x_kk(emulator: &mut Emulator, x: usize, kk: u8) -> Result<(), EmulatorError> {
+    let y: u8 = emulator.rng.next_byte();
 
     emulator.vx[x] = kk & y;
 
-    emulator
+    Ok(())
 }
 
 // Display n-byte sprite starting at memory location I at (Vx, Vy), set VF = collision.
-pub fn d_x_y_n(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
+// On SUPER-CHIP, Dxy0 displays a 16x16 sprite made of 32 bytes instead.
+pub fn d_x_y_n(emulator: &mut Emulator, x: usize, y: usize, n: u8) -> Result<(), EmulatorError> {
     let vx: usize = emulator.vx[x] as usize;
-    let y: usize = ((opcode & 0x00F0) >> 4) as usize;
     let vy: usize = emulator.vx[y] as usize;
-    let n: u16 = opcode & 0x000F;
-    let i: u16 = emulator.i;
+    let n: usize = n as usize;
+    let i: usize = emulator.i as usize;
 
-    //println!("┃ {opcode:04X} │ DRW       │ V{x:01X}, V{y:01X}, {n:01X} ┃");
+    let (rows, columns): (usize, usize) = if n == 0 && emulator.platform.supports_schip() {
+        (16, 16)
+    } else {
+        (n, 8)
+    };
+    let bytes_per_row: usize = columns / 8;
 
-    let sprite: &[u8] = &emulator.memory[(i as usize)..((i + n) as usize)];
-    let mut collission: bool = false;
-    
-    for (row, &byte) in sprite.iter().enumerate() {
-        let screen_y: usize = (vy + row) % 32;
-        
-        for bit in 0..8 {
-            let screen_x: usize = (vx + bit) % 64;
-            let sprite_bit: u8 = (byte >> (7 - bit )) & 1;
-
-            if emulator.display[screen_y][screen_x] == 1 && sprite_bit == 1 {
-                collission = true;
+    let width: usize = emulator.width();
+    let height: usize = emulator.height();
+
+    // The starting position always wraps, the clipping quirk decides what happens to the rest of the sprite.
+    let start_x: usize = vx % width;
+    let start_y: usize = vy % height;
+
+    // SUPER-CHIP 1.1 in high resolution counts the rows that collided or fell off the bottom instead.
+    let mut collided_rows: u8 = 0;
+    let mut clipped_rows: u8 = 0;
+
+    // XO-CHIP draws the sprite once per selected plane, each plane's data follows the previous one.
+    let sprite_size: usize = rows * bytes_per_row;
+    let mut sprite_start: usize = i;
+
+    for plane in 0..4 {
+        let plane_bit: u8 = 1 << plane;
+        if emulator.planes & plane_bit == 0 { continue }
+
+        for row in 0..rows {
+            if emulator.quirks.clipping && start_y + row >= height {
+                clipped_rows += 1;
+                continue;
+            }
+            let screen_y: usize = (start_y + row) % height;
+            let mut collision: bool = false;
+
+            for bit in 0..columns {
+                if emulator.quirks.clipping && start_x + bit >= width { break }
+                let screen_x: usize = (start_x + bit) % width;
+                let byte: u8 = emulator.read(sprite_start + row * bytes_per_row + bit / 8)?;
+                let sprite_bit: u8 = (byte >> (7 - bit % 8)) & 1;
+
+                if sprite_bit == 1 {
+                    if emulator.display[screen_y][screen_x] & plane_bit != 0 {
+                        collision = true;
+                    }
+
+                    emulator.display[screen_y][screen_x] ^= plane_bit;
+                }
             }
 
-            emulator.display[screen_y][screen_x] ^= sprite_bit;
+            if collision { collided_rows += 1 }
         }
+
+        sprite_start += sprite_size;
     }
 
-    if collission { emulator.vx[0xF] = 1 }
-    else { emulator.vx[0xF] = 0 }
+    if emulator.platform == Platform::Schip11 && emulator.hires {
+        emulator.vx[0xF] = collided_rows + clipped_rows;
+    } else if collided_rows > 0 {
+        emulator.vx[0xF] = 1
+    } else {
+        emulator.vx[0xF] = 0
+    }
 
     emulator.vram_updated = true;
 
-    emulator
+    // Display wait quirk, the VIP waited for the vertical blank interrupt before drawing.
+    if emulator.quirks.display_wait { emulator.waiting_for_vblank = true }
+
+    Ok(())
 }
 
 // Skip next instruction if key with the value of Vx is pressed.
-pub fn e_x_9e(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
+pub fn e_x_9e(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    if emulator.keypad[(emulator.vx[x] & 0x0F) as usize] { skip_next(emulator) }
 
-    //println!("┃ {opcode:04X} │ SKP       │ V{x:01X}        ┃");
+    Ok(())
+}
 
-    if emulator.keypad[emulator.vx[x] as usize] { emulator.pc += 2 }
+// Skip next instruction if key with the value of Vx is not pressed.
+pub fn e_x_a1(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    if !emulator.keypad[(emulator.vx[x] & 0x0F) as usize] { skip_next(emulator) }
 
-    emulator
+    Ok(())
 }
 
-// Skip next instruction if key with the value of Vx is not pressed.
-pub fn e_x_a1(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-    //println!("┃ {opcode:04X} │ SKNP      │ V{x:01X}        ┃");
+// Set I = nnnn, the address is the 16 bit word after the instruction (XO-CHIP).
+pub fn f_000(emulator: &mut Emulator) -> Result<(), EmulatorError> {
+    let nnnn: u16 = fetch(emulator)?;
 
-    if !emulator.keypad[emulator.vx[x] as usize] { emulator.pc += 2 }
+    emulator.i = nnnn;
+    emulator.pc = emulator.pc.wrapping_add(2);
 
-    emulator
+    Ok(())
 }
 
-// Set Vx = delay timer value.
-pub fn f_x_07(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
+// Select the drawing planes from the bit mask n (XO-CHIP).
+pub fn f_n_01(emulator: &mut Emulator, n: u8) -> Result<(), EmulatorError> {
+    emulator.planes = n;
 
-    //println!("┃ {opcode:04X} │ LD        │ V{x:01X}, DT    ┃");
+    Ok(())
+}
 
+// Load the 16 byte audio pattern buffer from memory starting at location I (XO-CHIP).
+pub fn f_002(emulator: &mut Emulator) -> Result<(), EmulatorError> {
+    let i: usize = emulator.i as usize;
+    for n in 0..16 {
+        emulator.audio_pattern[n] = emulator.read(i + n)?;
+    }
+
+    Ok(())
+}
+
+// Set Vx = delay timer value.
+pub fn f_x_07(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
     emulator.vx[x] = emulator.delay;
     
-    emulator
+    Ok(())
 }
 
 // Wait for a key press, store the value of the key in Vx.
-pub fn f_x_0a(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-
-    //println!("┃ {opcode:04X} │ LD        │ V{x:01X}, K      ┃");
-
-    if emulator.key_pressed == false {
+pub fn f_x_0a(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    if !emulator.key_pressed {
         for key in 0..emulator.keypad.len() {
             if emulator.keypad[key] {
                 emulator.vx[x] = key as u8;
@@ -389,109 +485,112 @@ pub fn f_x_0a(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
                 break;
             }
         }
-        emulator.pc -= 2;
+        emulator.pc = emulator.pc.wrapping_sub(2);
     } else {
-        if !emulator.keypad[emulator.vx[x] as usize] {
+        if !emulator.keypad[(emulator.vx[x] & 0x0F) as usize] {
             emulator.key_pressed = false;
         } else {
-            emulator.pc -= 2;
+            emulator.pc = emulator.pc.wrapping_sub(2);
         }
     }
     
-    emulator
+    Ok(())
 }
 
 // Set delay timer = Vx.
-pub fn f_x_15(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-
-    //println!("┃ {opcode:04X} │ LD        │ DT, V{x:01X}    ┃");
-
+pub fn f_x_15(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
     emulator.delay = emulator.vx[x];
 
-    emulator
+    Ok(())
 }
 
 // Set sound timer = Vx.
-pub fn f_x_18(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-
-    //println!("┃ {opcode:04X} │ LD        │ ST, V{x:01X}     ┃");
-
+pub fn f_x_18(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
     emulator.sound = emulator.vx[x];
 
-    emulator
+    Ok(())
 }
 
 // Set I = I + Vx.
-pub fn f_x_1e(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-
-    //println!("┃ {opcode:04X} │ ADD       │ [I], V{x:01X}   ┃");
-
-    emulator.i += emulator.vx[x] as u16;
+pub fn f_x_1e(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    emulator.i = emulator.i.wrapping_add(emulator.vx[x] as u16);
 
-    emulator
+    Ok(())
 }
 
 // Set I = location of sprite for digit Vx.
-pub fn f_x_29(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: u16 = (opcode & 0x0F00) >> 8;
-    let sprite_location: u16 = x * 5;
-
-    //println!("┃ {opcode:04X} │ LD        │ F, V{x:01X}      ┃");
+pub fn f_x_29(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    let sprite_location: u16 = (emulator.vx[x] & 0x0F) as u16 * 5;
 
     emulator.i = sprite_location;
 
-    emulator
+    Ok(())
 }
 
-// Store BCD representation of Vx in memory locations I, I+1, and I+2.
-pub fn f_x_33(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
+// Set I = location of the large sprite for digit Vx (SUPER-CHIP).
+pub fn f_x_30(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    let sprite_location: u16 = BIG_FONT_START + (emulator.vx[x] & 0x0F) as u16 * 10;
 
-    //println!("┃ {opcode:04X} │ LD        │ B, V{x:01X}      ┃");
+    emulator.i = sprite_location;
+
+    Ok(())
+}
 
-    emulator.memory[emulator.i as usize] = emulator.vx[x] / 100;
-    emulator.memory[emulator.i as usize + 1] = (emulator.vx[x] % 100) / 10;
-    emulator.memory[emulator.i as usize + 2] = emulator.vx[x] % 10;
+// Set the audio pattern playback pitch = Vx (XO-CHIP).
+pub fn f_x_3a(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    emulator.pitch = emulator.vx[x];
 
-    emulator
+    Ok(())
 }
 
-// Store registers V0 through Vx in memory starting at location I.
-pub fn f_x_55(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
+// Store BCD representation of Vx in memory locations I, I+1, and I+2.
+pub fn f_x_33(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    emulator.write(emulator.i as usize, emulator.vx[x] / 100)?;
+    emulator.write(emulator.i as usize + 1, (emulator.vx[x] % 100) / 10)?;
+    emulator.write(emulator.i as usize + 2, emulator.vx[x] % 10)?;
 
-    //println!("┃ {opcode:04X} │ LD        │ [I], V{x:01X}   ┃");
+    Ok(())
+}
 
+// Store registers V0 through Vx in memory starting at location I.
+pub fn f_x_55(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
     for n in 0..=x {
-        emulator.memory[emulator.i as usize] = emulator.vx[n];
-        emulator.i += 1; // Needed for memory quirk.
+        emulator.write(emulator.i as usize + n, emulator.vx[n])?;
     }
-    //emulator.i = x as u16;
 
-    emulator
+    // Memory quirk, the original interpreter left I incremented.
+    if emulator.quirks.memory { emulator.i = emulator.i.wrapping_add(x as u16 + 1) }
+
+    Ok(())
 }
 
 // Read registers V0 through Vx from memory starting at location I.
-pub fn f_x_65(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-
-    //println!("┃ {opcode:04X} │ LD        │ V{x:01X}, [I]   ┃");
-
+pub fn f_x_65(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
     for n in 0..=x {
-        emulator.vx[n] = emulator.memory[emulator.i as usize];
-        emulator.i += 1 // Needed for memory quirk.
+        emulator.vx[n] = emulator.read(emulator.i as usize + n)?;
     }
 
-    emulator
+    // Memory quirk, the original interpreter left I incremented.
+    if emulator.quirks.memory { emulator.i = emulator.i.wrapping_add(x as u16 + 1) }
+
+    Ok(())
 }
 
-// Placeholder for unknown instructions.
-pub fn unknown(opcode: u16, emulator: &mut Emulator) -> &mut Emulator {
-    println!("{opcode:04X} UNKNOWN");
+// Store V0 through Vx in the RPL user flags (SUPER-CHIP).
+pub fn f_x_75(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    emulator.rpl[0..=x].copy_from_slice(&emulator.vx[0..=x]);
 
+    Ok(())
+}
+
+// Read V0 through Vx from the RPL user flags (SUPER-CHIP).
+pub fn f_x_85(emulator: &mut Emulator, x: usize) -> Result<(), EmulatorError> {
+    emulator.vx[0..=x].copy_from_slice(&emulator.rpl[0..=x]);
+
+    Ok(())
+}
 
-    emulator
+// Unknown instructions stop execution, the program counter has already moved past them.
+pub fn unknown(emulator: &mut Emulator, opcode: u16) -> Result<(), EmulatorError> {
+    Err(EmulatorError::UnknownOpcode { pc: emulator.pc.wrapping_sub(2), opcode })
 }
\ No newline at end of file
@@ -0,0 +1,206 @@
+use crate::error::EmulatorError;
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// Colours for each combination of display planes as RGB, index 0 is the background.
+// Plain CHIP-8 only ever uses the first two.
+pub type Palette = [[u8; 3]; 16];
+
+pub const DEFAULT_PALETTE: Palette = [
+    [0, 0, 0],
+    [255, 179, 71],
+    [255, 102, 0],
+    [102, 34, 0],
+    [71, 179, 255],
+    [0, 160, 120],
+    [200, 60, 160],
+    [255, 255, 255],
+    [64, 64, 64],
+    [255, 230, 150],
+    [255, 150, 100],
+    [150, 90, 40],
+    [140, 200, 255],
+    [100, 220, 160],
+    [240, 130, 210],
+    [180, 180, 180],
+];
+
+// Green phosphor, like the monitors of the VIP's day. The planes are told apart by brightness.
+const GREEN_PALETTE: Palette = [
+    [4, 20, 8],
+    [51, 255, 102],
+    [0, 150, 60],
+    [190, 255, 200],
+    [20, 90, 40],
+    [120, 255, 150],
+    [0, 200, 90],
+    [230, 255, 235],
+    [10, 55, 25],
+    [80, 230, 120],
+    [30, 120, 60],
+    [160, 240, 170],
+    [60, 180, 90],
+    [100, 200, 120],
+    [40, 160, 80],
+    [140, 220, 150],
+];
+
+// The four shades of an early handheld's LCD, mixed for the planes past the first two.
+const LCD_PALETTE: Palette = [
+    [155, 188, 15],
+    [15, 56, 15],
+    [48, 98, 48],
+    [32, 77, 32],
+    [110, 145, 20],
+    [25, 66, 25],
+    [80, 120, 35],
+    [40, 87, 40],
+    [130, 165, 18],
+    [20, 60, 20],
+    [64, 109, 42],
+    [36, 82, 36],
+    [95, 132, 28],
+    [28, 70, 28],
+    [72, 115, 38],
+    [44, 92, 44],
+];
+
+// White on black, planes past the first get shades of grey.
+const WHITE_PALETTE: Palette = [
+    [0, 0, 0],
+    [255, 255, 255],
+    [170, 170, 170],
+    [220, 220, 220],
+    [85, 85, 85],
+    [235, 235, 235],
+    [130, 130, 130],
+    [200, 200, 200],
+    [45, 45, 45],
+    [245, 245, 245],
+    [150, 150, 150],
+    [210, 210, 210],
+    [110, 110, 110],
+    [225, 225, 225],
+    [190, 190, 190],
+    [240, 240, 240],
+];
+
+// Built in palettes, named for --theme and the base key in a themes file. The first is the default.
+pub const THEMES: [(&str, Palette); 4] = [
+    ("amber", DEFAULT_PALETTE),
+    ("green", GREEN_PALETTE),
+    ("lcd", LCD_PALETTE),
+    ("white", WHITE_PALETTE),
+];
+
+// A built in palette by name, ignoring case.
+pub fn theme(name: &str) -> Option<Palette> {
+    THEMES.iter().find(|(theme, _)| theme.eq_ignore_ascii_case(name)).map(|(_, palette)| *palette)
+}
+
+// A colour written as #RRGGBB, the # is optional.
+pub fn parse_colour(colour: &str) -> Option<[u8; 3]> {
+    let hex: &str = colour.strip_prefix('#').unwrap_or(colour);
+    if hex.len() != 6 { return None }
+
+    let value: u32 = u32::from_str_radix(hex, 16).ok()?;
+    let [_, r, g, b] = value.to_be_bytes();
+
+    Some([r, g, b])
+}
+
+// Parses a comma separated list of colours, used for the --colours option.
+pub fn parse_colours(list: &str) -> Result<Vec<[u8; 3]>, String> {
+    if list.trim().is_empty() { return Ok(Vec::new()) }
+
+    let colours: Vec<[u8; 3]> = list
+        .split(',')
+        .map(|colour| parse_colour(colour.trim()).ok_or_else(|| format!("'{}' isn't a colour, expected #RRGGBB", colour.trim())))
+        .collect::<Result<_, _>>()?;
+
+    if colours.len() > 16 {
+        return Err(format!("{} colours given, a palette only has 16", colours.len()));
+    }
+
+    Ok(colours)
+}
+
+// The palette with the given colours in place of its first few entries.
+pub fn overlay(mut palette: Palette, colours: &[[u8; 3]]) -> Palette {
+    for (entry, colour) in palette.iter_mut().zip(colours) {
+        *entry = *colour;
+    }
+    palette
+}
+
+// A themes file, e.g.
+//
+//     [paper]
+//     colours = ["#F0EAD6", "#202020"]
+//
+//     [night]
+//     base = "green"
+//     colours = ["#000000"]
+//
+// Each table is a theme named after it, its colours replace the first few of its base, which is amber if not given.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeEntry {
+    base: Option<String>,
+
+    #[serde(default)]
+    colours: Vec<String>,
+}
+
+// Adds themes to a list, one with the same name as a theme already there takes its place instead.
+pub fn add_themes(themes: &mut Vec<(String, Palette)>, added: Vec<(String, Palette)>) {
+    for (name, palette) in added {
+        match themes.iter_mut().find(|(existing, _)| existing.eq_ignore_ascii_case(&name)) {
+            Some(theme) => *theme = (name, palette),
+            None => themes.push((name, palette)),
+        }
+    }
+}
+
+// Loads the themes in a file, sorted by name.
+pub fn load_themes(path: &Path) -> Result<Vec<(String, Palette)>, EmulatorError> {
+    let bad_config = |reason: String| EmulatorError::BadConfig { reason };
+
+    let text: String = fs::read_to_string(path)?;
+    let entries: BTreeMap<String, ThemeEntry> = toml::from_str(&text).map_err(|error| bad_config(error.message().to_string()))?;
+
+    entries
+        .into_iter()
+        .map(|(name, entry)| {
+            let base: &str = entry.base.as_deref().unwrap_or(THEMES[0].0);
+            let palette: Palette = theme(base).ok_or_else(|| bad_config(format!("theme '{name}' has an unknown base '{base}'")))?;
+            let colours: Vec<[u8; 3]> = parse_colours(&entry.colours.join(",")).map_err(|reason| bad_config(format!("theme '{name}': {reason}")))?;
+
+            Ok((name, overlay(palette, &colours)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn added_themes_replace_ones_with_the_same_name() {
+        let mut themes: Vec<(String, Palette)> = THEMES.iter().map(|(name, palette)| (name.to_string(), *palette)).collect();
+        let red: Palette = overlay(DEFAULT_PALETTE, &[[255, 0, 0]]);
+
+        add_themes(&mut themes, vec![("Green".to_string(), red), ("red".to_string(), red)]);
+
+        assert_eq!(themes.len(), THEMES.len() + 1);
+        assert_eq!(themes.iter().filter(|(name, _)| name.eq_ignore_ascii_case("green")).count(), 1);
+
+        // Replaced where it was, so F5 still cycles in the same order.
+        let green: usize = THEMES.iter().position(|(name, _)| *name == "green").unwrap();
+        assert_eq!(themes[green], ("Green".to_string(), red));
+        assert_eq!(themes.last().unwrap(), &("red".to_string(), red));
+    }
+}
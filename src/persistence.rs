@@ -0,0 +1,73 @@
+use crate::palette::Palette;
+use crate::system::Emulator;
+
+// Fades pixels out over a few frames after they're erased, like the phosphor of an old monitor.
+// Games move sprites by XORing them off and back on, so without it they flicker. Only what's drawn changes,
+// the emulator's display is left alone.
+pub struct Persistence {
+    pub strength: f32, // How much of an erased pixel's brightness is left after each frame, from 0 to 1.
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 3]>, // Colour on screen, row by row.
+    fading: bool, // Whether any pixel is still on its way to the background.
+}
+
+impl Persistence {
+    pub fn new(strength: f32) -> Self {
+        Self { strength: strength.clamp(0.0, 1.0), width: 0, height: 0, pixels: Vec::new(), fading: false }
+    }
+
+    // Moves on by one 60Hz frame, call it once per emulated frame however often the screen is drawn.
+    // Lit pixels show straight away, erased ones fade towards the background.
+    pub fn update(&mut self, emulator: &Emulator, palette: &Palette) {
+        let (width, height) = (emulator.width(), emulator.height());
+
+        // The old pixels don't line up with a new resolution, so it starts over.
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.pixels = vec![palette[0].map(f32::from); width * height];
+        }
+
+        self.fading = false;
+
+        for y in 0..height {
+            for x in 0..width {
+                let index: usize = emulator.display[y][x] as usize & 0x0F;
+                let target: [f32; 3] = palette[index].map(f32::from);
+                let pixel: &mut [f32; 3] = &mut self.pixels[y * width + x];
+
+                if index != 0 {
+                    *pixel = target;
+                    continue;
+                }
+
+                for (channel, target) in pixel.iter_mut().zip(target) {
+                    *channel = target + (*channel - target) * self.strength;
+
+                    // Close enough not to be seen.
+                    if (*channel - target).abs() < 1.0 {
+                        *channel = target;
+                    } else {
+                        self.fading = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // Whether the screen will still change without the display changing, so it needs drawing every frame.
+    pub fn is_fading(&self) -> bool {
+        self.fading
+    }
+
+    // Width and height of the display as of the last update.
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    // Colour of a pixel as of the last update.
+    pub fn colour(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x].map(|channel| channel.round() as u8)
+    }
+}
@@ -0,0 +1,120 @@
+use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+use crate::system::{FONT, VIP_FONT};
+
+use std::str::FromStr;
+
+// Well known CHIP-8 variants, each one bundles the settings a ROM written for it expects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Platform {
+    #[default]
+    Vip, // CHIP-8 on the COSMAC VIP.
+    Chip48, // CHIP-48 on the HP-48 calculators.
+    Schip10, // SUPER-CHIP 1.0.
+    Schip11, // SUPER-CHIP 1.1.
+    XoChip, // Octo's XO-CHIP extension.
+}
+
+impl Platform {
+    // Quirks the platform's interpreter had.
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Vip => Quirks::vip(),
+            Platform::Chip48 => Quirks::chip48(),
+            Platform::Schip10 | Platform::Schip11 => Quirks::schip(),
+            Platform::XoChip => Quirks::xochip(),
+        }
+    }
+
+    // Bytes of addressable memory.
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::XoChip => 0x10000,
+            _ => 0x1000,
+        }
+    }
+
+    // How many return addresses fit on the stack.
+    pub fn stack_depth(self) -> usize {
+        match self {
+            Platform::Vip => 12,
+            _ => 16,
+        }
+    }
+
+    // Highest display resolution the platform supports as (width, height).
+    pub fn resolution(self) -> (usize, usize) {
+        match self {
+            Platform::Vip | Platform::Chip48 => (64, 32),
+            _ => (128, 64),
+        }
+    }
+
+    // Whether the SUPER-CHIP instructions, large font and 16x16 sprites are available.
+    pub fn supports_schip(self) -> bool {
+        !matches!(self, Platform::Vip | Platform::Chip48)
+    }
+
+    // Whether the XO-CHIP instructions, extra memory and display planes are available.
+    pub fn supports_xochip(self) -> bool {
+        self == Platform::XoChip
+    }
+
+    // Whether the platform has an instruction, ones from a later extension are unknown opcodes on it.
+    pub fn supports(self, instruction: Instruction) -> bool {
+        match instruction {
+            Instruction::Exit
+            | Instruction::LowRes
+            | Instruction::HighRes
+            | Instruction::BigFont { .. }
+            | Instruction::SaveFlags { .. }
+            | Instruction::LoadFlags { .. } => self.supports_schip(),
+            // Scrolling was added in SUPER-CHIP 1.1.
+            Instruction::ScrollDown { .. } | Instruction::ScrollRight | Instruction::ScrollLeft => {
+                matches!(self, Platform::Schip11 | Platform::XoChip)
+            }
+            Instruction::ScrollUp { .. }
+            | Instruction::SaveRange { .. }
+            | Instruction::LoadRange { .. }
+            | Instruction::LoadLongI
+            | Instruction::Plane { .. }
+            | Instruction::LoadAudio
+            | Instruction::Pitch { .. } => self.supports_xochip(),
+            _ => true,
+        }
+    }
+
+    // Small hex font loaded at the start of memory.
+    pub fn font(self) -> &'static [u8; 80] {
+        match self {
+            Platform::Vip => &VIP_FONT,
+            _ => &FONT,
+        }
+    }
+
+    // Default number of instructions executed per 60Hz frame.
+    pub fn instructions_per_frame(self) -> u32 {
+        match self {
+            Platform::Vip => 15,
+            Platform::Chip48 => 15,
+            Platform::Schip10 | Platform::Schip11 => 30,
+            Platform::XoChip => 1000,
+        }
+    }
+}
+
+// Parses a platform name, used for the --platform option.
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "vip" | "chip8" | "chip-8" => Ok(Platform::Vip),
+            "chip48" | "chip-48" => Ok(Platform::Chip48),
+            "schip1.0" | "schip10" => Ok(Platform::Schip10),
+            "schip1.1" | "schip11" | "schip" | "superchip" => Ok(Platform::Schip11),
+            "xochip" | "xo-chip" => Ok(Platform::XoChip),
+            _ => Err(format!("unknown platform '{s}', expected vip, chip48, schip1.0, schip1.1 or xochip")),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+// Behaviours that differ between CHIP-8 implementations.
+// Named after the quirks checked by Timendus' 5-quirks.ch8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    pub vf_reset: bool, // 8xy1, 8xy2 and 8xy3 reset VF to 0.
+    pub memory: bool, // Fx55 and Fx65 leave I pointing past the last register.
+    pub display_wait: bool, // Dxyn waits for the next frame before execution continues.
+    pub clipping: bool, // Sprites are clipped at the screen edges instead of wrapping around.
+    pub shifting: bool, // 8xy6 and 8xyE shift Vx in place instead of copying Vy first.
+    pub jumping: bool, // Bnnn jumps to xnn + Vx instead of nnn + V0.
+}
+
+// Quirk names accepted by --quirk.
+pub const QUIRK_NAMES: [&str; 6] = ["vf-reset", "memory", "display-wait", "clipping", "shifting", "jumping"];
+
+impl Quirks {
+    // The original COSMAC VIP interpreter.
+    pub const fn vip() -> Self {
+        Self {
+            vf_reset: true,
+            memory: true,
+            display_wait: true,
+            clipping: true,
+            shifting: false,
+            jumping: false,
+        }
+    }
+
+    // CHIP-48 on the HP-48 calculators.
+    pub const fn chip48() -> Self {
+        Self {
+            vf_reset: false,
+            memory: false,
+            display_wait: false,
+            clipping: true,
+            shifting: true,
+            jumping: true,
+        }
+    }
+
+    // SUPER-CHIP, which kept the CHIP-48 behaviour.
+    pub const fn schip() -> Self {
+        Self::chip48()
+    }
+
+    // XO-CHIP, which went back to the VIP's arithmetic but wraps sprites and doesn't wait.
+    pub const fn xochip() -> Self {
+        Self {
+            vf_reset: false,
+            memory: true,
+            display_wait: false,
+            clipping: false,
+            shifting: false,
+            jumping: false,
+        }
+    }
+
+    // Looks up a single quirk by its command line name.
+    fn flag(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "vf-reset" => Some(&mut self.vf_reset),
+            "memory" => Some(&mut self.memory),
+            "display-wait" => Some(&mut self.display_wait),
+            "clipping" => Some(&mut self.clipping),
+            "shifting" => Some(&mut self.shifting),
+            "jumping" => Some(&mut self.jumping),
+            _ => None,
+        }
+    }
+
+    // Applies an override in the form "name=on" or "name=off", e.g. "shifting=on".
+    pub fn apply(&mut self, setting: &str) -> Result<(), String> {
+        let (name, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=on|off, got '{setting}'"))?;
+
+        let value: bool = match value {
+            "on" | "true" | "1" => true,
+            "off" | "false" | "0" => false,
+            _ => return Err(format!("expected on or off for quirk '{name}', got '{value}'")),
+        };
+
+        match self.flag(name) {
+            Some(flag) => {
+                *flag = value;
+                Ok(())
+            }
+            None => Err(format!("unknown quirk '{name}', expected one of: {}", QUIRK_NAMES.join(", "))),
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::vip()
+    }
+}
+
+// Parses a quirk profile name, used for the --quirks option.
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "vip" | "chip8" | "chip-8" => Ok(Self::vip()),
+            "chip48" | "chip-48" => Ok(Self::chip48()),
+            "schip" | "superchip" | "super-chip" => Ok(Self::schip()),
+            "xochip" | "xo-chip" => Ok(Self::xochip()),
+            _ => Err(format!("unknown quirk profile '{s}', expected vip, chip48, schip or xochip")),
+        }
+    }
+}
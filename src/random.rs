@@ -0,0 +1,96 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Where 0xCxkk gets its random bytes from. The emulator owns one, so it can be swapped for a
+// fixed sequence in tests or for a routine like the COSMAC VIP's.
+pub trait RandomSource {
+    // The next random byte, before it's ANDed with kk.
+    fn next_byte(&mut self) -> u8;
+
+    // Called once per instruction, for sources that depend on timing.
+    fn tick(&mut self) {}
+}
+
+// A seeded generator, the default. The same seed always gives the same bytes.
+pub struct SeededRandom {
+    rng: StdRng,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed) }
+    }
+
+    // Seeded from the operating system, for when a run doesn't need repeating.
+    pub fn from_entropy() -> Self {
+        Self { rng: StdRng::from_entropy() }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.rng.gen()
+    }
+}
+
+// Hands out a fixed list of bytes, starting again from the beginning once it runs out.
+pub struct FixedRandom {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl FixedRandom {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes, position: 0 }
+    }
+}
+
+impl RandomSource for FixedRandom {
+    fn next_byte(&mut self) -> u8 {
+        if self.bytes.is_empty() { return 0 }
+
+        let byte: u8 = self.bytes[self.position % self.bytes.len()];
+        self.position += 1;
+
+        byte
+    }
+}
+
+// Works the same way as the COSMAC VIP interpreter's routine, which had no real random numbers.
+// Register R9 counts up as the interpreter runs, and 0xCxkk adds its low byte to a byte from a table indexed
+// by its high byte, keeping the sum as the new high byte. The VIP read the table from its own interpreter code,
+// which isn't included here, so the numbers won't match a real VIP's. Like on the VIP, though, they depend on
+// how many instructions ran between calls.
+pub struct VipRandom {
+    r9: u16, // The VIP's R9 register.
+    page: [u8; 256], // Table the routine reads from, in place of the interpreter's code.
+}
+
+impl VipRandom {
+    pub fn new(seed: u16, page: [u8; 256]) -> Self {
+        Self { r9: seed, page }
+    }
+
+    // With the table filled from a seed, for when there's no interpreter code to hand.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut table: SeededRandom = SeededRandom::new(seed);
+        let page: [u8; 256] = std::array::from_fn(|_| table.next_byte());
+
+        Self::new(seed as u16, page)
+    }
+}
+
+impl RandomSource for VipRandom {
+    fn next_byte(&mut self) -> u8 {
+        let [high, low] = self.r9.to_be_bytes();
+        let byte: u8 = self.page[high as usize].wrapping_add(low);
+
+        self.r9 = u16::from_be_bytes([byte, low]);
+
+        byte
+    }
+
+    fn tick(&mut self) {
+        self.r9 = self.r9.wrapping_add(1);
+    }
+}
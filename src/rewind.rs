@@ -0,0 +1,186 @@
+use crate::system::Emulator;
+
+use std::collections::VecDeque;
+
+// Keeps the last few seconds of frames so the game can be run backwards.
+// Only the newest frame is stored in full, every older one is the XOR of it with the frame after it.
+// Most of memory and the display stay the same between frames, so those deltas are nearly all zeros and compress well.
+pub struct Rewind {
+    latest: Vec<u8>, // Save state of the newest frame.
+    deltas: VecDeque<Vec<u8>>, // Compressed deltas, the back turns latest into the frame before it.
+    capacity: usize, // Most frames that can be rewound.
+}
+
+impl Rewind {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            latest: Vec::new(),
+            deltas: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Records a frame, dropping the oldest one once the buffer is full.
+    pub fn push(&mut self, emulator: &Emulator) {
+        let mut state: Vec<u8> = Vec::with_capacity(self.latest.len());
+        emulator.save_state(&mut state).expect("writing to a Vec can't fail");
+
+        // States from another platform can't be diffed, so the history starts again.
+        if state.len() != self.latest.len() {
+            self.deltas.clear();
+        } else if self.capacity > 0 {
+            if self.deltas.len() == self.capacity {
+                self.deltas.pop_front();
+            }
+            self.deltas.push_back(compress(&xor(&self.latest, &state)));
+        }
+
+        self.latest = state;
+    }
+
+    // Steps back to the frame before the newest one, or None once there's nothing older.
+    pub fn pop(&mut self) -> Option<Emulator> {
+        let delta: Vec<u8> = self.deltas.pop_back()?;
+        self.latest = xor(&self.latest, &decompress(&delta, self.latest.len()));
+
+        Emulator::load_state(&mut self.latest.as_slice()).ok()
+    }
+
+    // How many frames can currently be rewound.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    // Rough number of bytes the buffer is using.
+    pub fn memory_used(&self) -> usize {
+        self.latest.len() + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+// Run length encodes the zeros, as pairs of (zero count, literal count) followed by the literal bytes.
+fn compress(delta: &[u8]) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::new();
+    let mut pos: usize = 0;
+
+    while pos < delta.len() {
+        let zeros: usize = delta[pos..].iter().take_while(|&&byte| byte == 0).count();
+        pos += zeros;
+
+        let literals: usize = delta[pos..].iter().take_while(|&&byte| byte != 0).count();
+
+        write_length(&mut output, zeros);
+        write_length(&mut output, literals);
+        output.extend_from_slice(&delta[pos..pos + literals]);
+
+        pos += literals;
+    }
+
+    output
+}
+
+fn decompress(data: &[u8], size: usize) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::with_capacity(size);
+    let mut pos: usize = 0;
+
+    while pos < data.len() {
+        let zeros: usize = read_length(data, &mut pos);
+        let literals: usize = read_length(data, &mut pos);
+
+        output.resize(output.len() + zeros, 0);
+        output.extend_from_slice(&data[pos..pos + literals]);
+        pos += literals;
+    }
+
+    output.resize(size, 0);
+    output
+}
+
+// Lengths are stored seven bits at a time, the top bit is set when more follow.
+fn write_length(output: &mut Vec<u8>, mut length: usize) {
+    while length >= 0x80 {
+        output.push((length & 0x7F) as u8 | 0x80);
+        length >>= 7;
+    }
+    output.push(length as u8);
+}
+
+fn read_length(data: &[u8], pos: &mut usize) -> usize {
+    let mut length: usize = 0;
+    let mut shift: u32 = 0;
+
+    loop {
+        let byte: u8 = data[*pos];
+        *pos += 1;
+
+        length |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 { return length }
+
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Platform;
+
+    #[test]
+    fn compress_round_trip() {
+        let long_run: Vec<u8> = [vec![0; 300], vec![7; 200], vec![0; 20_000], vec![1]].concat();
+        let deltas: [&[u8]; 6] = [&[], &[0; 10], &[1, 2, 3], &[0, 0, 5, 0, 6, 6, 0], &[9, 0, 0, 0], &long_run];
+
+        for delta in deltas {
+            assert_eq!(decompress(&compress(delta), delta.len()), delta);
+        }
+
+        assert!(compress(&long_run).len() < 220);
+    }
+
+    #[test]
+    fn lengths_round_trip() {
+        for length in [0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, 1 << 30] {
+            let mut data: Vec<u8> = Vec::new();
+            write_length(&mut data, length);
+
+            let mut pos: usize = 0;
+            assert_eq!(read_length(&data, &mut pos), length);
+            assert_eq!(pos, data.len());
+        }
+    }
+
+    #[test]
+    fn pop_goes_back_a_frame_at_a_time() {
+        let mut emulator: Emulator = Emulator::new(Platform::Schip11);
+        let mut rewind: Rewind = Rewind::new(3);
+        let mut states: Vec<Vec<u8>> = Vec::new();
+
+        for frame in 0..5 {
+            emulator.vx[0] = frame;
+            emulator.memory[0x300 + frame as usize] = 0xFF;
+            rewind.push(&emulator);
+
+            let mut state: Vec<u8> = Vec::new();
+            emulator.save_state(&mut state).unwrap();
+            states.push(state);
+        }
+
+        assert_eq!(rewind.len(), 3);
+
+        for expected in states[1..4].iter().rev() {
+            let mut state: Vec<u8> = Vec::new();
+            rewind.pop().unwrap().save_state(&mut state).unwrap();
+            assert_eq!(&state, expected);
+        }
+
+        assert!(rewind.is_empty());
+        assert!(rewind.pop().is_none());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::error::EmulatorError;
+use crate::palette::Palette;
+use crate::system::Emulator;
+
+use std::io::Write;
+
+// The visible part of the display as RGB bytes, each pixel scaled up to a scale x scale square.
+// Returns the width and height along with the pixels.
+pub fn render_rgb(emulator: &Emulator, palette: &Palette, scale: usize) -> (usize, usize, Vec<u8>) {
+    let width: usize = emulator.width() * scale;
+    let height: usize = emulator.height() * scale;
+    let mut pixels: Vec<u8> = Vec::with_capacity(width * height * 3);
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel: usize = emulator.display[y / scale][x / scale] as usize;
+            pixels.extend_from_slice(&palette[pixel & 0x0F]);
+        }
+    }
+
+    (width, height, pixels)
+}
+
+// Writes the display as a PNG in the palette's colours.
+pub fn write_png(emulator: &Emulator, palette: &Palette, scale: usize, writer: impl Write) -> Result<(), EmulatorError> {
+    let (width, height, pixels) = render_rgb(emulator, palette, scale);
+
+    let mut encoder: png::Encoder<_> = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut png_writer = encoder.write_header()?;
+    png_writer.write_image_data(&pixels)?;
+
+    Ok(())
+}
+
+// Writes the display as a binary PBM, which needs no libraries to make or read.
+// PBM is black and white only, so any lit pixel is black whatever plane it's on.
+pub fn write_pbm(emulator: &Emulator, scale: usize, mut writer: impl Write) -> Result<(), EmulatorError> {
+    let width: usize = emulator.width() * scale;
+    let height: usize = emulator.height() * scale;
+
+    let mut data: Vec<u8> = format!("P4\n{width} {height}\n").into_bytes();
+
+    // Rows are packed eight pixels to a byte, most significant bit first, padded to a whole byte.
+    for y in 0..height {
+        let mut row: Vec<u8> = vec![0; width.div_ceil(8)];
+
+        for x in 0..width {
+            if emulator.display[y / scale][x / scale] != 0 {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+
+        data.extend_from_slice(&row);
+    }
+
+    writer.write_all(&data)?;
+
+    Ok(())
+}
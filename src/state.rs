@@ -0,0 +1,281 @@
+use crate::error::EmulatorError;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::system::{Emulator, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use std::io::{Read, Write};
+
+// Every save state starts with this, followed by the format version.
+const MAGIC: &[u8; 4] = b"C8SS";
+
+// Bumped whenever the layout changes, older states are rejected instead of being misread.
+pub const STATE_VERSION: u8 = 1;
+
+// Platforms are stored as a single byte.
+const PLATFORMS: [Platform; 5] = [Platform::Vip, Platform::Chip48, Platform::Schip10, Platform::Schip11, Platform::XoChip];
+
+// Save states are the whole emulator written field by field, multi-byte values are big endian like the CHIP-8 itself.
+// The random number source isn't part of it, a loaded state starts with a fresh one for the caller to swap its own into.
+impl Emulator {
+    pub fn save_state(&self, writer: &mut impl Write) -> Result<(), EmulatorError> {
+        let mut data: Vec<u8> = Vec::with_capacity(self.memory.len() + SCREEN_WIDTH * SCREEN_HEIGHT + 128);
+
+        data.extend_from_slice(MAGIC);
+        data.push(STATE_VERSION);
+
+        let platform: usize = PLATFORMS.iter().position(|&p| p == self.platform).unwrap();
+        data.push(platform as u8);
+
+        let quirks: [bool; 6] = [
+            self.quirks.vf_reset,
+            self.quirks.memory,
+            self.quirks.display_wait,
+            self.quirks.clipping,
+            self.quirks.shifting,
+            self.quirks.jumping,
+        ];
+        data.extend(quirks.map(u8::from));
+
+        data.extend_from_slice(&(self.memory.len() as u32).to_be_bytes());
+        data.extend_from_slice(&self.memory);
+
+        data.extend_from_slice(&self.pc.to_be_bytes());
+        data.push(self.sp);
+        data.extend_from_slice(&self.vx);
+        data.extend_from_slice(&self.i.to_be_bytes());
+        data.push(self.delay);
+        data.push(self.sound);
+
+        data.push(self.stack.len() as u8);
+        for addr in &self.stack {
+            data.extend_from_slice(&addr.to_be_bytes());
+        }
+
+        for row in &self.display {
+            data.extend_from_slice(row);
+        }
+        data.push(self.planes);
+        data.push(self.hires as u8);
+
+        data.extend(self.keypad.map(u8::from));
+        data.push(self.key_pressed as u8);
+        data.push(self.waiting_for_vblank as u8);
+        data.extend_from_slice(&self.rpl);
+        data.push(self.exited as u8);
+        data.extend_from_slice(&self.audio_pattern);
+        data.push(self.pitch);
+
+        writer.write_all(&data)?;
+
+        Ok(())
+    }
+
+    pub fn load_state(reader: &mut impl Read) -> Result<Emulator, EmulatorError> {
+        let mut data: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let mut state: StateReader = StateReader { data: &data, offset: 0 };
+
+        if state.bytes(4)? != MAGIC {
+            return Err(bad_state("not a save state"));
+        }
+
+        let version: u8 = state.byte()?;
+        if version != STATE_VERSION {
+            return Err(bad_state(&format!("version {version} isn't supported, expected {STATE_VERSION}")));
+        }
+
+        let platform: Platform = *PLATFORMS.get(state.byte()? as usize).ok_or_else(|| bad_state("unknown platform"))?;
+        let mut emulator: Emulator = Emulator::new(platform);
+
+        emulator.quirks = Quirks {
+            vf_reset: state.flag()?,
+            memory: state.flag()?,
+            display_wait: state.flag()?,
+            clipping: state.flag()?,
+            shifting: state.flag()?,
+            jumping: state.flag()?,
+        };
+
+        let memory_size: usize = u32::from_be_bytes(state.array()?) as usize;
+        if memory_size != platform.memory_size() {
+            return Err(bad_state("memory size doesn't match the platform"));
+        }
+        emulator.memory.copy_from_slice(state.bytes(memory_size)?);
+
+        emulator.pc = state.word()?;
+        emulator.sp = state.byte()?;
+        emulator.vx = state.array()?;
+        emulator.i = state.word()?;
+        emulator.delay = state.byte()?;
+        emulator.sound = state.byte()?;
+
+        let stack_depth: usize = state.byte()? as usize;
+        if stack_depth != platform.stack_depth() || emulator.sp as usize > stack_depth {
+            return Err(bad_state("stack doesn't match the platform"));
+        }
+        for addr in emulator.stack.iter_mut() {
+            *addr = state.word()?;
+        }
+
+        for row in emulator.display.iter_mut() {
+            *row = state.array()?;
+        }
+        emulator.planes = state.byte()?;
+        emulator.hires = state.flag()?;
+
+        for key in emulator.keypad.iter_mut() {
+            *key = state.flag()?;
+        }
+        emulator.key_pressed = state.flag()?;
+        emulator.waiting_for_vblank = state.flag()?;
+        emulator.rpl = state.array()?;
+        emulator.exited = state.flag()?;
+        emulator.audio_pattern = state.array()?;
+        emulator.pitch = state.byte()?;
+
+        if state.offset != data.len() {
+            return Err(bad_state("unexpected data at the end"));
+        }
+
+        // The frontend has never drawn this display.
+        emulator.vram_updated = true;
+
+        Ok(emulator)
+    }
+}
+
+fn bad_state(reason: &str) -> EmulatorError {
+    EmulatorError::BadSaveState { reason: reason.to_string() }
+}
+
+// Walks through a save state, failing cleanly if it's cut short.
+struct StateReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl StateReader<'_> {
+    fn bytes(&mut self, count: usize) -> Result<&[u8], EmulatorError> {
+        let bytes: &[u8] = self.data.get(self.offset..self.offset + count).ok_or_else(|| bad_state("file is truncated"))?;
+        self.offset += count;
+
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], EmulatorError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn byte(&mut self) -> Result<u8, EmulatorError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, EmulatorError> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn flag(&mut self) -> Result<bool, EmulatorError> {
+        Ok(self.byte()? != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An emulator with something in every field, so a field that isn't saved or loaded shows up.
+    fn busy_emulator() -> Emulator {
+        let mut emulator: Emulator = Emulator::new(Platform::XoChip);
+
+        emulator.quirks.jumping = !emulator.quirks.jumping;
+        emulator.memory[0x200] = 0x12;
+        emulator.memory[0xFFFF] = 0x34;
+        emulator.pc = 0x2468;
+        emulator.sp = 2;
+        emulator.stack[0] = 0x0202;
+        emulator.stack[1] = 0x0F0E;
+        emulator.vx = std::array::from_fn(|x| x as u8 * 17);
+        emulator.i = 0xABCD;
+        emulator.delay = 9;
+        emulator.sound = 3;
+        emulator.display[5][7] = 3;
+        emulator.display[63][127] = 1;
+        emulator.planes = 2;
+        emulator.hires = true;
+        emulator.keypad[0xA] = true;
+        emulator.key_pressed = true;
+        emulator.rpl[7] = 0x77;
+        emulator.audio_pattern[15] = 0xF0;
+        emulator.pitch = 100;
+
+        emulator
+    }
+
+    fn save(emulator: &Emulator) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        emulator.save_state(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        let original: Emulator = busy_emulator();
+        let data: Vec<u8> = save(&original);
+
+        let loaded: Emulator = Emulator::load_state(&mut data.as_slice()).unwrap();
+
+        assert_eq!(save(&loaded), data);
+        assert_eq!(loaded.platform, Platform::XoChip);
+        assert_eq!(loaded.quirks, original.quirks);
+        assert_eq!((loaded.pc, loaded.i, loaded.sp), (0x2468, 0xABCD, 2));
+        assert_eq!(&loaded.stack[..2], &[0x0202, 0x0F0E]);
+        assert_eq!(loaded.display[5][7], 3);
+        assert!(loaded.vram_updated);
+    }
+
+    #[test]
+    fn every_platform() {
+        for platform in PLATFORMS {
+            let data: Vec<u8> = save(&Emulator::new(platform));
+            let loaded: Emulator = Emulator::load_state(&mut data.as_slice()).unwrap();
+
+            assert_eq!(loaded.platform, platform);
+            assert_eq!(save(&loaded), data);
+        }
+    }
+
+    #[test]
+    fn bad_states_are_rejected() {
+        let data: Vec<u8> = save(&busy_emulator());
+
+        let mut wrong_magic: Vec<u8> = data.clone();
+        wrong_magic[0] = b'X';
+
+        let mut wrong_version: Vec<u8> = data.clone();
+        wrong_version[4] = STATE_VERSION + 1;
+
+        let mut wrong_platform: Vec<u8> = data.clone();
+        wrong_platform[5] = PLATFORMS.len() as u8;
+
+        let mut trailing: Vec<u8> = data.clone();
+        trailing.push(0);
+
+        let cases: [(&[u8], &str); 6] = [
+            (&wrong_magic, "not a save state"),
+            (&wrong_version, "isn't supported"),
+            (&wrong_platform, "unknown platform"),
+            (&trailing, "unexpected data"),
+            (&data[..data.len() - 1], "truncated"),
+            (&[], "truncated"),
+        ];
+
+        for (state, reason) in cases {
+            match Emulator::load_state(&mut &state[..]) {
+                Err(EmulatorError::BadSaveState { reason: error }) => assert!(error.contains(reason), "{error}"),
+                Err(error) => panic!("expected a bad save state, got {error}"),
+                Ok(_) => panic!("expected '{reason}' but the state loaded"),
+            }
+        }
+    }
+}
@@ -1,7 +1,10 @@
+use crate::error::EmulatorError;
+use crate::instruction::{decode, Instruction};
 use crate::opcodes::*;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::random::{RandomSource, SeededRandom};
 
-use sdl2::render::Canvas;
-use sdl2::video::Window;
 use std::fs;
 
 // The common CHIP-8 font set.
@@ -24,147 +27,268 @@ pub const FONT: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80  // F
 ];
 
-// CHIP-8 resolution is 64 x 32.
-const SCREEN_WIDTH: usize = 64;
-const SCREEN_HEIGHT: usize = 32;
+// The font from the COSMAC VIP's interpreter, which differs from the common set in a few digits.
+pub const VIP_FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0x70, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80  // F
+];
+
+// SUPER-CHIP's large font, 8x10 sprites for each hex digit.
+pub const BIG_FONT: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0  // F
+];
+
+// The large font sits straight after the small one.
+pub const BIG_FONT_START: u16 = 0x0050;
+
+// CHIP-8 resolution is 64 x 32, SUPER-CHIP's high resolution mode doubles it to 128 x 64.
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+pub const SCREEN_WIDTH: usize = 128;
+pub const SCREEN_HEIGHT: usize = 64;
 
 // Emulalator struct (yes it's technically an interpreter but this isn't worth changing now).
 pub struct Emulator {
-    pub memory: [u8; 4096], // 4096 bytes of memory.
+    pub memory: Vec<u8>, // 4096 bytes of memory, or 64KiB for XO-CHIP.
     pub pc: u16, // 16 bit program counter.
     pub sp: u8, // 8 bit stack pointer.
     pub vx: [u8; 16], // 8 bit V0-VF registers.
     pub i: u16, // 16 bit index counter.
     pub delay: u8, // 8 bit delay timer.
     pub sound: u8, // 8 bit sound timer.
-    pub stack: [u16; 16], // 16 bit stack array.
-    pub display: [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT], // 64 x 32 display array.
+    pub stack: Vec<u16>, // 16 bit stack array, as deep as the platform allows.
+    pub display: [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT], // 128 x 64 display array, low resolution only uses the top left 64 x 32.
+    pub planes: u8, // Bit mask of the display planes being drawn to, each pixel holds one bit per plane.
+    pub hires: bool, // SUPER-CHIP high resolution mode.
     pub keypad: [bool; 16], // 16 key CHIP-8 keypad array.
-    pub canvas: Canvas<Window>, // Canvas for drawing during execution.
-    pub vram_updated: bool, // Flag for drawing the screen.
+    pub vram_updated: bool, // Flag for the frontend to redraw the screen.
     pub key_pressed: bool, // Flag for 0xFx0A.
+    pub platform: Platform, // Which CHIP-8 variant is being emulated.
+    pub quirks: Quirks, // Which interpreter's behaviour the ambiguous opcodes follow.
+    pub waiting_for_vblank: bool, // Set by 0xDxyn when the display wait quirk is on.
+    pub rpl: [u8; 16], // SUPER-CHIP's RPL user flags for 0xFx75 and 0xFx85.
+    pub exited: bool, // Set by 0x00FD.
+    pub audio_pattern: [u8; 16], // XO-CHIP's 128 bit audio pattern buffer.
+    pub pitch: u8, // XO-CHIP's audio playback pitch register.
+    pub rng: Box<dyn RandomSource>, // Random numbers for 0xCxkk, seeded so a run can be replayed exactly.
 }
 
 // Creates a new emulator instance (again I know, interpreter haha).
-// Only input that needs to be specified is the canvas.
+// The emulator is pure state, drawing is left to whichever frontend owns it.
+// Memory size, stack depth, font and quirks all come from the platform.
 impl Emulator {
-    pub fn new(canvas: Canvas<Window>) -> Self {
+    pub fn new(platform: Platform) -> Self {
+        let mut memory: Vec<u8> = vec![0; platform.memory_size()];
+
+        // Loads the fonts into unused memory.
+        memory[0x0000..0x0050].copy_from_slice(platform.font());
+        if platform.supports_schip() {
+            memory[BIG_FONT_START as usize..BIG_FONT_START as usize + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
+        }
+
         Self {
-            memory: [0; 4096],
+            memory,
             pc: 0x0200,
             sp: 0,
             vx: [0; 16],
             i: 0,
             delay: 0,
             sound: 0,
-            stack: [0; 16],
+            stack: vec![0; platform.stack_depth()],
             display: [[0; SCREEN_WIDTH]; SCREEN_HEIGHT],
+            planes: 1,
+            hires: false,
             keypad: [false; 16],
-            canvas: canvas,
             vram_updated: false,
             key_pressed: false,
+            platform,
+            quirks: platform.quirks(),
+            waiting_for_vblank: false,
+            rpl: [0; 16],
+            exited: false,
+            audio_pattern: [0; 16],
+            pitch: 64,
+            rng: Box::new(SeededRandom::from_entropy()),
         }
     }
+
+    // Reads a byte of memory, failing instead of panicking past the end.
+    pub fn read(&self, addr: usize) -> Result<u8, EmulatorError> {
+        self.memory.get(addr).copied().ok_or(EmulatorError::MemoryOutOfBounds { addr })
+    }
+
+    // Writes a byte of memory, failing instead of panicking past the end.
+    pub fn write(&mut self, addr: usize, value: u8) -> Result<(), EmulatorError> {
+        match self.memory.get_mut(addr) {
+            Some(byte) => {
+                *byte = value;
+                Ok(())
+            }
+            None => Err(EmulatorError::MemoryOutOfBounds { addr }),
+        }
+    }
+
+    // Width of the display in the current resolution.
+    pub fn width(&self) -> usize {
+        if self.hires { self.platform.resolution().0 } else { LORES_WIDTH }
+    }
+
+    // Height of the display in the current resolution.
+    pub fn height(&self) -> usize {
+        if self.hires { self.platform.resolution().1 } else { LORES_HEIGHT }
+    }
+
+    // Restarts the random numbers from a seed, the same seed always gives the same 0xCxkk results.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Box::new(SeededRandom::new(seed));
+    }
 }
 
-// Function for loading the emulator struct, then loading the ROM and font into memory.
-pub fn load(path: &str, canvas: Canvas<Window>) -> Emulator {
-    let mut emulator: Emulator = Emulator::new(canvas);
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new(Platform::default())
+    }
+}
 
-    let data: Vec<u8> = match fs::read(path) {
-        Ok(data) => data,
-        Err(error) => panic!("Problem opening file: {error:?}")
-    };
+// Function for loading the emulator struct, then loading the ROM into memory.
+pub fn load(path: &str, platform: Platform) -> Result<Emulator, EmulatorError> {
+    load_rom(&fs::read(path)?, platform)
+}
 
-    // Loads the font into unused memory.
-    emulator.memory[0x0000..0x0050].copy_from_slice(&FONT);
+// Same as load for a ROM that's already been read, e.g. to look it up in the database before picking a platform.
+pub fn load_rom(data: &[u8], platform: Platform) -> Result<Emulator, EmulatorError> {
+    let mut emulator: Emulator = Emulator::new(platform);
 
     // See Cowgod's technical reference for the memory.
-    emulator.memory[0x0200..0x0200 + data.len()].copy_from_slice(&data);
+    let max: usize = emulator.memory.len() - 0x0200;
+    if data.len() > max {
+        return Err(EmulatorError::RomTooLarge { size: data.len(), max });
+    }
+
+    emulator.memory[0x0200..0x0200 + data.len()].copy_from_slice(data);
+
+    Ok(emulator)
+}
 
-    emulator
+// Decrements the delay and sound timers, called at 60Hz by the frontend.
+pub fn tick_timers(emulator: &mut Emulator) {
+    if emulator.delay != 0 { emulator.delay -= 1 }
+    if emulator.sound != 0 { emulator.sound -= 1 }
 }
 
 // Fetching next instruction from memory.
-pub fn fetch(emulator: &mut Emulator) -> u16 {
+pub fn fetch(emulator: &mut Emulator) -> Result<u16, EmulatorError> {
     // Instructions are two bytes long.
     // First byte is at the program counter value in memory.
     // Second byte is at the program counter value + 1 in memory.
-    let instruction: u16 = (emulator.memory[emulator.pc as usize] as u16) << 8
-                         | (emulator.memory[emulator.pc as usize + 1] as u16);
+    let instruction: u16 = (emulator.read(emulator.pc as usize)? as u16) << 8
+                         | (emulator.read(emulator.pc as usize + 1)? as u16);
 
-    instruction
+    Ok(instruction)
 }
 
-// Function for decoding and running instructions.
-pub fn decode(
-    emulator: &mut Emulator, 
-    instruction: u16,
-) -> &mut Emulator {
-    emulator.pc += 2; // Incrememnt the program counter for next instruction.
-
-    // Common parts of instructions for matching.
-    let most_significant: u16 = instruction & 0xF000;
+// Runs an already decoded instruction.
+pub fn execute(
+    emulator: &mut Emulator,
+    instruction: Instruction,
+) -> Result<(), EmulatorError> {
+    // Opcodes from an extension the platform doesn't have are as unknown as any other.
+    if !emulator.platform.supports(instruction) {
+        return Err(EmulatorError::UnknownOpcode { pc: emulator.pc, opcode: fetch(emulator)? });
+    }
 
-    let suffix: u16 = instruction & 0x00FF;
-    let least_significant: u16 = instruction & 0x000F;
+    emulator.pc = emulator.pc.wrapping_add(2); // Incrememnt the program counter for next instruction.
+    emulator.rng.tick();
 
     // Big match statement for passing instructions through to their respective functions.
-    return match most_significant {
-        0x0000 => {
-            match instruction {
-                0x00E0 => e_0(emulator), 
-                0x00EE => e_e(emulator), 
-                _ => unknown(instruction, emulator),
-            }
-        }
-        0x1000 => one_nnn(instruction, emulator), 
-        0x2000 => two_nnn(instruction, emulator),
-        0x3000 => three_x_kk(instruction, emulator), 
-        0x4000 => four_x_kk(instruction, emulator), 
-        0x5000 => five_x_y_0(instruction, emulator), 
-        0x6000 => six_x_kk(instruction, emulator),
-        0x7000 => seven_x_kk(instruction, emulator),
-        0x8000 => {
-            match least_significant {
-                0x0000 => eight_x_y_0(instruction, emulator),
-                0x0001 => eight_x_y_1(instruction, emulator),
-                0x0002 => eight_x_y_2(instruction, emulator),
-                0x0003 => eight_x_y_3(instruction, emulator),
-                0x0004 => eight_x_y_4(instruction, emulator),
-                0x0005 => eight_x_y_5(instruction, emulator),
-                0x0006 => eight_x_y_6(instruction, emulator),
-                0x0007 => eight_x_y_7(instruction, emulator),
-                0x000E => eight_x_y_e(instruction, emulator),
-                _ => unknown(instruction, emulator),
-            }
-        }
-        0x9000 => nine_x_y_0(instruction, emulator),
-        0xA000 => a_nnn(instruction, emulator),
-        0xB000 => b_nnn(instruction, emulator),
-        0xC000 => c_x_kk(instruction, emulator),
-        0xD000 => d_x_y_n(instruction, emulator),
-        0xE000 => {
-            match suffix {
-                0x009E => e_x_9e(instruction, emulator),
-                0x00A1 => e_x_a1(instruction, emulator),
-                _ => unknown(instruction, emulator),
-            }
-        }
-        0xF000 => {
-            match suffix {
-                0x0007 => f_x_07(instruction, emulator),
-                0x000A => f_x_0a(instruction, emulator),
-                0x0015 => f_x_15(instruction, emulator),
-                0x0018 => f_x_18(instruction, emulator),
-                0x0029 => f_x_29(instruction, emulator),
-                0x001E => f_x_1e(instruction, emulator),
-                0x0033 => f_x_33(instruction, emulator),
-                0x0055 => f_x_55(instruction, emulator),
-                0x0065 => f_x_65(instruction, emulator),
-                _ => unknown(instruction, emulator),
-            }
-        }
-        _ => unknown(instruction, emulator),
-    };
-}
\ No newline at end of file
+    match instruction {
+        Instruction::ScrollDown { n } => c_n(emulator, n),
+        Instruction::ScrollUp { n } => d_n(emulator, n),
+        Instruction::Cls => e_0(emulator),
+        Instruction::Ret => e_e(emulator),
+        Instruction::ScrollRight => f_b(emulator),
+        Instruction::ScrollLeft => f_c(emulator),
+        Instruction::Exit => f_d(emulator),
+        Instruction::LowRes => f_e(emulator),
+        Instruction::HighRes => f_f(emulator),
+        Instruction::Jump { nnn } => one_nnn(emulator, nnn),
+        Instruction::Call { nnn } => two_nnn(emulator, nnn),
+        Instruction::SkipEqByte { x, kk } => three_x_kk(emulator, x, kk),
+        Instruction::SkipNeByte { x, kk } => four_x_kk(emulator, x, kk),
+        Instruction::SkipEqReg { x, y } => five_x_y_0(emulator, x, y),
+        Instruction::SaveRange { x, y } => five_x_y_2(emulator, x, y),
+        Instruction::LoadRange { x, y } => five_x_y_3(emulator, x, y),
+        Instruction::LoadByte { x, kk } => six_x_kk(emulator, x, kk),
+        Instruction::AddByte { x, kk } => seven_x_kk(emulator, x, kk),
+        Instruction::Move { x, y } => eight_x_y_0(emulator, x, y),
+        Instruction::Or { x, y } => eight_x_y_1(emulator, x, y),
+        Instruction::And { x, y } => eight_x_y_2(emulator, x, y),
+        Instruction::Xor { x, y } => eight_x_y_3(emulator, x, y),
+        Instruction::Add { x, y } => eight_x_y_4(emulator, x, y),
+        Instruction::Sub { x, y } => eight_x_y_5(emulator, x, y),
+        Instruction::ShiftRight { x, y } => eight_x_y_6(emulator, x, y),
+        Instruction::SubN { x, y } => eight_x_y_7(emulator, x, y),
+        Instruction::ShiftLeft { x, y } => eight_x_y_e(emulator, x, y),
+        Instruction::SkipNeReg { x, y } => nine_x_y_0(emulator, x, y),
+        Instruction::LoadI { nnn } => a_nnn(emulator, nnn),
+        Instruction::JumpOffset { nnn } => b_nnn(emulator, nnn),
+        Instruction::Random { x, kk } => c_x_kk(emulator, x, kk),
+        Instruction::Draw { x, y, n } => d_x_y_n(emulator, x, y, n),
+        Instruction::SkipKey { x } => e_x_9e(emulator, x),
+        Instruction::SkipNotKey { x } => e_x_a1(emulator, x),
+        Instruction::LoadLongI => f_000(emulator),
+        Instruction::Plane { n } => f_n_01(emulator, n),
+        Instruction::LoadAudio => f_002(emulator),
+        Instruction::LoadDelay { x } => f_x_07(emulator, x),
+        Instruction::WaitKey { x } => f_x_0a(emulator, x),
+        Instruction::SetDelay { x } => f_x_15(emulator, x),
+        Instruction::SetSound { x } => f_x_18(emulator, x),
+        Instruction::AddI { x } => f_x_1e(emulator, x),
+        Instruction::Font { x } => f_x_29(emulator, x),
+        Instruction::BigFont { x } => f_x_30(emulator, x),
+        Instruction::Bcd { x } => f_x_33(emulator, x),
+        Instruction::Pitch { x } => f_x_3a(emulator, x),
+        Instruction::Store { x } => f_x_55(emulator, x),
+        Instruction::Load { x } => f_x_65(emulator, x),
+        Instruction::SaveFlags { x } => f_x_75(emulator, x),
+        Instruction::LoadFlags { x } => f_x_85(emulator, x),
+        Instruction::Unknown { opcode } => unknown(emulator, opcode),
+    }
+}
+
+// Fetches, decodes and executes a single instruction.
+pub fn step(emulator: &mut Emulator) -> Result<Instruction, EmulatorError> {
+    let instruction: Instruction = decode(fetch(emulator)?);
+    execute(emulator, instruction)?;
+
+    Ok(instruction)
+}
@@ -0,0 +1,342 @@
+use crate::error::EmulatorError;
+use crate::palette::Palette;
+use crate::system::Emulator;
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// Bytes in an IEND chunk, which has no data.
+const IEND_LENGTH: u64 = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoFormat {
+    Gif,
+    Apng,
+}
+
+impl VideoFormat {
+    // Picks the format from a file extension, .gif for GIF and .png or .apng for APNG.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension: String = path.extension()?.to_string_lossy().to_ascii_lowercase();
+
+        match extension.as_str() {
+            "gif" => Some(VideoFormat::Gif),
+            "png" | "apng" => Some(VideoFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+enum Output<W: Write + Seek> {
+    Gif(gif::Encoder<W>),
+    // APNG wants the frame count before the first frame, so frames are written as they come, each followed by
+    // an IEND that the next one writes over, and the count in the acTL chunk is filled in again every time.
+    // The file is a complete APNG after every frame, even if finish is never called.
+    Apng { writer: W, actl: u64, frames: u32, sequence: u32 },
+}
+
+// Records the display as an animated image, one image per frame the display changed on.
+// Pixels are stored as palette indices, so a 16 colour palette covers every combination of planes.
+pub struct VideoRecorder<W: Write + Seek> {
+    output: Output<W>,
+    width: usize, // The platform's highest resolution, every image is stored at this size.
+    height: usize,
+    scale: usize, // How much bigger each pixel is in the file.
+    palette: Palette,
+    start: u64, // Frame the recording started on.
+    pending: Option<(Vec<u8>, u64)>, // Newest image and its frame, written once it's known how long it stays up.
+}
+
+impl VideoRecorder<BufWriter<File>> {
+    // Starts recording to a file, in the format its extension asks for.
+    pub fn create(path: &Path, emulator: &Emulator, palette: &Palette, scale: usize, frame: u64) -> Result<Self, EmulatorError> {
+        let format: VideoFormat = VideoFormat::from_path(path).ok_or_else(|| {
+            EmulatorError::Io(std::io::Error::other("video files need to end in .gif, .png or .apng"))
+        })?;
+
+        VideoRecorder::new(BufWriter::new(File::create(path)?), format, emulator, palette, scale, frame)
+    }
+}
+
+impl<W: Write + Seek> VideoRecorder<W> {
+    // Starts recording on the given 60Hz frame, with the display as it is now as the first image.
+    pub fn new(writer: W, format: VideoFormat, emulator: &Emulator, palette: &Palette, scale: usize, frame: u64) -> Result<Self, EmulatorError> {
+        let (width, height) = emulator.platform.resolution();
+
+        let output: Output<W> = match format {
+            VideoFormat::Gif => {
+                let (file_width, file_height) = ((width * scale) as u16, (height * scale) as u16);
+                let mut encoder: gif::Encoder<W> = gif::Encoder::new(writer, file_width, file_height, palette.as_flattened())?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+                Output::Gif(encoder)
+            }
+            VideoFormat::Apng => {
+                let (file_width, file_height) = ((width * scale) as u32, (height * scale) as u32);
+                let (writer, actl) = apng_header(writer, file_width, file_height, palette)?;
+                Output::Apng { writer, actl, frames: 0, sequence: 0 }
+            }
+        };
+
+        let mut recorder: VideoRecorder<W> = Self { output, width, height, scale, palette: *palette, start: frame, pending: None };
+        recorder.frame(emulator, frame)?;
+
+        Ok(recorder)
+    }
+
+    // Adds the display as it is on this frame, call it whenever vram_updated is set.
+    pub fn frame(&mut self, emulator: &Emulator, frame: u64) -> Result<(), EmulatorError> {
+        let image: Vec<u8> = self.image(emulator);
+
+        if let Some((previous, shown)) = self.pending.take() {
+            self.write(previous, shown, frame)?;
+        }
+        self.pending = Some((image, frame));
+
+        Ok(())
+    }
+
+    // Writes the last image, which stays up until the given frame, and finishes the file.
+    pub fn finish(mut self, frame: u64) -> Result<(), EmulatorError> {
+        if let Some((previous, shown)) = self.pending.take() {
+            self.write(previous, shown, frame.max(shown + 1))?;
+        }
+
+        match self.output {
+            // Taking the writer back out writes the GIF trailer.
+            Output::Gif(encoder) => {
+                encoder.into_inner()?.flush()?;
+            }
+            Output::Apng { mut writer, .. } => {
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Writes an image that was shown from one frame until another.
+    fn write(&mut self, image: Vec<u8>, shown: u64, until: u64) -> Result<(), EmulatorError> {
+        match &mut self.output {
+            Output::Gif(encoder) => {
+                // GIF delays are in hundredths of a second, which 60Hz frames don't divide into evenly.
+                // Rounding the time since the start rather than each delay keeps the drift from adding up.
+                let centiseconds = |frame: u64| ((frame - self.start) * 100 + 30) / 60;
+
+                let (file_width, file_height) = ((self.width * self.scale) as u16, (self.height * self.scale) as u16);
+                let mut gif_frame: gif::Frame = gif::Frame::from_indexed_pixels(file_width, file_height, scale_image(&image, self.width, self.scale), None);
+                gif_frame.delay = (centiseconds(until) - centiseconds(shown)).min(u16::MAX as u64) as u16;
+                encoder.write_frame(&gif_frame)?;
+            }
+            Output::Apng { writer, actl, frames, sequence } => {
+                let (file_width, file_height) = ((self.width * self.scale) as u32, (self.height * self.scale) as u32);
+                let data: Vec<u8> = compress_image(&scale_image(&image, self.width, self.scale), file_width, file_height, &self.palette)?;
+
+                // APNG delays are a fraction of a second, so 60Hz frames fit exactly.
+                let delay: u16 = (until - shown).min(u16::MAX as u64) as u16;
+
+                if *frames > 0 {
+                    writer.seek(SeekFrom::Current(-(IEND_LENGTH as i64)))?;
+                }
+
+                let mut fctl: Vec<u8> = Vec::with_capacity(26);
+                fctl.extend_from_slice(&sequence.to_be_bytes());
+                fctl.extend_from_slice(&file_width.to_be_bytes());
+                fctl.extend_from_slice(&file_height.to_be_bytes());
+                fctl.extend_from_slice(&[0; 8]); // X and Y offset.
+                fctl.extend_from_slice(&delay.to_be_bytes());
+                fctl.extend_from_slice(&60u16.to_be_bytes());
+                fctl.extend_from_slice(&[0, 0]); // Leave the frame as it is and draw over it.
+                write_chunk(writer, b"fcTL", &fctl)?;
+                *sequence += 1;
+
+                // The first frame doubles as the still image for viewers without APNG, the rest are fdAT.
+                if *frames == 0 {
+                    write_chunk(writer, b"IDAT", &data)?;
+                } else {
+                    write_chunk(writer, b"fdAT", &[&sequence.to_be_bytes()[..], &data].concat())?;
+                    *sequence += 1;
+                }
+
+                write_chunk(writer, b"IEND", &[])?;
+
+                *frames += 1;
+                write_actl(writer, *actl, *frames)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // The display as palette indices, lores pixels are doubled up so every image is the same size.
+    fn image(&self, emulator: &Emulator) -> Vec<u8> {
+        let scale_x: usize = self.width / emulator.width();
+        let scale_y: usize = self.height / emulator.height();
+        let mut image: Vec<u8> = Vec::with_capacity(self.width * self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                image.push(emulator.display[y / scale_y][x / scale_x] & 0x0F);
+            }
+        }
+
+        image
+    }
+}
+
+// Writes the PNG signature, IHDR, acTL and PLTE, returning where the acTL chunk is so its frame count can be updated.
+fn apng_header<W: Write + Seek>(mut writer: W, width: u32, height: u32, palette: &Palette) -> Result<(W, u64), EmulatorError> {
+    writer.write_all(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A])?;
+
+    let mut ihdr: Vec<u8> = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[8, 3, 0, 0, 0]); // Eight bit indexed colour, no interlacing.
+    write_chunk(&mut writer, b"IHDR", &ihdr)?;
+
+    let actl: u64 = writer.stream_position()?;
+    write_actl(&mut writer, actl, 0)?;
+
+    write_chunk(&mut writer, b"PLTE", palette.as_flattened())?;
+
+    Ok((writer, actl))
+}
+
+// Rewrites the acTL chunk with the number of frames so far, looping forever, and goes back to the end.
+fn write_actl<W: Write + Seek>(writer: &mut W, position: u64, frames: u32) -> Result<(), EmulatorError> {
+    let end: u64 = writer.stream_position()?;
+
+    writer.seek(SeekFrom::Start(position))?;
+    write_chunk(writer, b"acTL", &[frames.to_be_bytes(), 0u32.to_be_bytes()].concat())?;
+
+    if end > position {
+        writer.seek(SeekFrom::Start(end))?;
+    }
+
+    Ok(())
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> Result<(), EmulatorError> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&crc32(&[&kind[..], data].concat()).to_be_bytes())?;
+
+    Ok(())
+}
+
+// An image's compressed pixel data, the png crate encodes it as a still image and its IDAT chunks are taken out.
+fn compress_image(image: &[u8], width: u32, height: u32, palette: &Palette) -> Result<Vec<u8>, EmulatorError> {
+    let mut png_data: Vec<u8> = Vec::new();
+
+    let mut encoder: png::Encoder<&mut Vec<u8>> = png::Encoder::new(&mut png_data, width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette.as_flattened());
+
+    let mut png_writer = encoder.write_header()?;
+    png_writer.write_image_data(image)?;
+    png_writer.finish()?;
+
+    // Chunks are a length, a four byte type, the data and a CRC, after the eight byte signature.
+    let mut data: Vec<u8> = Vec::new();
+    let mut pos: usize = 8;
+    while pos + 8 <= png_data.len() {
+        let length: usize = u32::from_be_bytes(png_data[pos..pos + 4].try_into().unwrap()) as usize;
+        if &png_data[pos + 4..pos + 8] == b"IDAT" {
+            data.extend_from_slice(&png_data[pos + 8..pos + 8 + length]);
+        }
+        pos += 12 + length;
+    }
+
+    Ok(data)
+}
+
+// The CRC-32 PNG chunks end with.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+// Blows an image up to the size it's written at.
+fn scale_image(image: &[u8], width: usize, scale: usize) -> Vec<u8> {
+    let mut scaled: Vec<u8> = Vec::with_capacity(image.len() * scale * scale);
+
+    for row in image.chunks(width) {
+        let wide: Vec<u8> = row.iter().flat_map(|&pixel| [pixel].repeat(scale)).collect();
+        for _ in 0..scale {
+            scaled.extend_from_slice(&wide);
+        }
+    }
+
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::DEFAULT_PALETTE;
+    use crate::platform::Platform;
+
+    use std::io::Cursor;
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+
+    // Records a pixel appearing on frames 12, 15 and 21 of a recording started on frame 10.
+    fn record(finish: bool) -> Vec<u8> {
+        let mut emulator: Emulator = Emulator::new(Platform::Vip);
+        let mut file: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+        let mut recorder = VideoRecorder::new(&mut file, VideoFormat::Apng, &emulator, &DEFAULT_PALETTE, 2, 10).unwrap();
+
+        for frame in [12, 15, 21] {
+            emulator.display[0][frame as usize] = 1;
+            recorder.frame(&emulator, frame).unwrap();
+        }
+
+        if finish {
+            recorder.finish(30).unwrap();
+        } else {
+            drop(recorder);
+        }
+
+        file.into_inner()
+    }
+
+    // The delay of every frame in an APNG, in 60ths of a second.
+    fn delays(file: Vec<u8>) -> Vec<u16> {
+        let mut reader = png::Decoder::new(Cursor::new(file)).read_info().unwrap();
+        let frames: u32 = reader.info().animation_control.unwrap().num_frames;
+        let mut image: Vec<u8> = vec![0; reader.output_buffer_size()];
+
+        (0..frames)
+            .map(|_| {
+                reader.next_frame(&mut image).unwrap();
+                let control: png::FrameControl = reader.info().frame_control.unwrap();
+                assert_eq!(control.delay_den, 60);
+                control.delay_num
+            })
+            .collect()
+    }
+
+    #[test]
+    fn apng_round_trip() {
+        assert_eq!(delays(record(true)), [2, 3, 6, 9]);
+    }
+
+    // Each frame is on disk as soon as the next one arrives, so a recording that's never finished still plays.
+    #[test]
+    fn apng_without_finish() {
+        assert_eq!(delays(record(false)), [2, 3, 6]);
+    }
+}
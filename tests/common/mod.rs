@@ -0,0 +1,73 @@
+// Shared by the headless tests: runs an emulator frame by frame like main.rs does, and compares its screen
+// against a golden image in tests/golden. Running with BLESS=1 writes the golden images from the current output
+// instead of comparing, so check them by eye before committing them.
+
+use lolei_chip8::system::*;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// A key going down or up on a frame.
+pub struct KeyEvent {
+    pub frame: u32,
+    pub key: usize,
+    pub down: bool,
+}
+
+// Runs a number of frames, calling before_frame at the start of each one.
+pub fn run_frames(emulator: &mut Emulator, name: &str, frames: u32, keys: &[KeyEvent], mut before_frame: impl FnMut(&mut Emulator)) {
+    for frame in 0..frames {
+        before_frame(emulator);
+
+        for event in keys.iter().filter(|event| event.frame == frame) {
+            emulator.keypad[event.key] = event.down;
+        }
+
+        // Same as the frame loop in main.rs.
+        emulator.waiting_for_vblank = false;
+        let mut i: u32 = 0;
+        while i < emulator.platform.instructions_per_frame() && !emulator.waiting_for_vblank && !emulator.exited {
+            if let Err(error) = step(emulator) {
+                panic!("{name} stopped on frame {frame}: {error}");
+            }
+            i += 1;
+        }
+
+        tick_timers(emulator);
+    }
+}
+
+// Compares the screen against tests/golden/<name>.txt, or writes it there with BLESS=1.
+pub fn check_golden(emulator: &Emulator, name: &str) {
+    let golden: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{name}.txt"));
+    let screen: String = screen(emulator);
+
+    if std::env::var_os("BLESS").is_some() {
+        fs::create_dir_all(golden.parent().unwrap()).unwrap();
+        fs::write(&golden, &screen).unwrap();
+        return;
+    }
+
+    let expected: String = fs::read_to_string(&golden)
+        .unwrap_or_else(|_| panic!("{} is missing, run with BLESS=1 to create it", golden.display()));
+
+    assert!(screen == expected, "{name} doesn't match {}, got:\n{screen}", golden.display());
+}
+
+// The visible part of the display, . for unlit pixels, # for the first plane, and the plane mask for the rest.
+fn screen(emulator: &Emulator) -> String {
+    let mut output: String = String::new();
+
+    for row in &emulator.display[..emulator.height()] {
+        for &pixel in &row[..emulator.width()] {
+            output.push(match pixel {
+                0 => '.',
+                1 => '#',
+                _ => char::from_digit(pixel as u32, 16).unwrap_or('?'),
+            });
+        }
+        output.push('\n');
+    }
+
+    output
+}
@@ -0,0 +1,10 @@
+#!/bin/sh
+# Downloads Timendus' test suite ROMs into tests/roms, for `cargo test --test timendus -- --ignored`.
+set -e
+
+cd "$(dirname "$0")"
+mkdir -p roms
+
+for rom in 1-chip8-logo 2-ibm-logo 3-corax+ 4-flags 5-quirks 6-keypad 7-beep; do
+    curl -fsSL -o "roms/$rom.ch8" "https://raw.githubusercontent.com/Timendus/chip8-test-suite/main/bin/$rom.ch8"
+done
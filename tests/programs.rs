@@ -0,0 +1,46 @@
+// Small programs in tests/programs, assembled with the crate's own assembler and run headless, with the screen
+// compared against golden images like the Timendus tests. Unlike those, these need nothing downloaded.
+
+mod common;
+
+use common::{check_golden, run_frames};
+
+use lolei_chip8::asm::assemble_file;
+use lolei_chip8::platform::Platform;
+use lolei_chip8::random::FixedRandom;
+use lolei_chip8::system::Emulator;
+
+use std::path::Path;
+
+fn run(name: &str, platform: Platform, frames: u32) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("programs").join(format!("{name}.asm"));
+    let rom: Vec<u8> = assemble_file(&path).unwrap_or_else(|error| panic!("{error}"));
+
+    let mut emulator: Emulator = Emulator::new(platform);
+    emulator.memory[0x200..0x200 + rom.len()].copy_from_slice(&rom);
+    emulator.rng = Box::new(FixedRandom::new(vec![0x5A]));
+
+    run_frames(&mut emulator, name, frames, &[], |_| {});
+
+    check_golden(&emulator, name);
+}
+
+#[test]
+fn font() {
+    run("font", Platform::Vip, 30);
+}
+
+#[test]
+fn arithmetic() {
+    run("arithmetic", Platform::Vip, 30);
+}
+
+#[test]
+fn schip() {
+    run("schip", Platform::Schip11, 30);
+}
+
+#[test]
+fn xochip() {
+    run("xochip", Platform::XoChip, 10);
+}
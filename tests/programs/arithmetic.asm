@@ -0,0 +1,71 @@
+; Works out six sums and prints each result in decimal followed by VF, three rows of two.
+;
+;   044 1   127 0           200 + 100 carries, 100 + 27 doesn't
+;   226 0   030 1           50 - 80 borrows, 80 - 50 doesn't
+;   002 1   144 1           5 >> 1 and 200 << 1 both shift out a 1
+
+    LD V3, 200
+    LD V7, 100
+    ADD V3, V7
+    LD V4, VF
+    LD V5, 2
+    LD V6, 2
+    CALL print
+
+    LD V3, 100
+    LD V7, 27
+    ADD V3, V7
+    LD V4, VF
+    LD V5, 34
+    CALL print
+
+    LD V3, 50
+    LD V7, 80
+    SUB V3, V7
+    LD V4, VF
+    LD V5, 2
+    LD V6, 12
+    CALL print
+
+    LD V3, 50
+    LD V7, 80
+    SUBN V3, V7
+    LD V4, VF
+    LD V5, 34
+    CALL print
+
+    LD V3, 5
+    SHR V3
+    LD V4, VF
+    LD V5, 2
+    LD V6, 22
+    CALL print
+
+    LD V3, 200
+    SHL V3
+    LD V4, VF
+    LD V5, 34
+    CALL print
+end:
+    JP end
+
+; Prints V3 as three digits and V4 as one, starting at V5, V6.
+print:
+    LD I, digits
+    LD B, V3
+    LD V2, [I]
+    LD F, V0
+    DRW V5, V6, 5
+    ADD V5, 5
+    LD F, V1
+    DRW V5, V6, 5
+    ADD V5, 5
+    LD F, V2
+    DRW V5, V6, 5
+    ADD V5, 8
+    LD F, V4
+    DRW V5, V6, 5
+    RET
+
+digits:
+    DB 0, 0, 0
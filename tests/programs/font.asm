@@ -0,0 +1,19 @@
+; Draws the sixteen font digits in two rows of eight, each one found with LD F, Vx.
+
+    LD V0, 0                ; Digit.
+    LD V1, 2                ; X.
+    LD V2, 4                ; Y.
+loop:
+    LD F, V0
+    DRW V1, V2, 5
+    ADD V0, 1
+    ADD V1, 8
+    SE V0, 8
+    JP next
+    LD V1, 2                ; Second row.
+    LD V2, 14
+next:
+    SE V0, 16
+    JP loop
+end:
+    JP end
@@ -0,0 +1,28 @@
+; SUPER-CHIP: switches to high resolution, draws the large digits 0 to 9 and a 16 x 16 sprite,
+; then scrolls everything right by 4 and down by 6 before exiting.
+
+    HIGH
+    LD V0, 0                ; Digit.
+    LD V1, 4                ; X.
+    LD V2, 8                ; Y.
+digit:
+    LD HF, V0
+    DRW V1, V2, 10
+    ADD V0, 1
+    ADD V1, 12
+    SE V0, 10
+    JP digit
+
+    LD I, box
+    LD V1, 52
+    LD V2, 30
+    DRW V1, V2, 0
+
+    SCR
+    SCD 6
+    EXIT
+
+; A square outline with a cross through it.
+box:
+    DW #FFFF, #C003, #A005, #9009, #8811, #8421, #8241, #8181
+    DW #8181, #8241, #8421, #8811, #9009, #A005, #C003, #FFFF
@@ -0,0 +1,28 @@
+; XO-CHIP: draws overlapping squares on the first plane, the second plane and both, with the sprite data
+; reached through LD I, LONG, then scrolls the screen up by 4.
+
+    LD V1, 8
+    LD V2, 12
+
+    PLANE 1
+    LD I, LONG square
+    DRW V1, V2, 8
+
+    PLANE 2
+    ADD V1, 4
+    ADD V2, 4
+    LD I, LONG square
+    DRW V1, V2, 8
+
+    PLANE 3                 ; Both planes, the sprite is read twice.
+    ADD V1, 16
+    LD I, LONG square
+    DRW V1, V2, 8
+
+    SCU 4
+end:
+    JP end
+
+square:
+    DB #FF, #81, #81, #81, #81, #81, #81, #FF
+    DB #FF, #FF, #C3, #C3, #C3, #C3, #FF, #FF
@@ -0,0 +1,108 @@
+// Runs Timendus' CHIP-8 test suite headless and compares the screen at the end against golden images.
+// https://github.com/Timendus/chip8-test-suite
+//
+// The ROMs aren't part of the repository, tests/fetch-roms.sh downloads them into tests/roms. A test whose ROM
+// is missing is skipped with a message, unless TIMENDUS_REQUIRED is set, e.g. in CI, where it fails instead.
+// The golden images go in tests/golden named after the ROM, see common/mod.rs for writing them with BLESS=1.
+
+mod common;
+
+use common::{check_golden, run_frames, KeyEvent};
+
+use lolei_chip8::platform::Platform;
+use lolei_chip8::random::FixedRandom;
+use lolei_chip8::system::*;
+
+use std::path::{Path, PathBuf};
+
+// One run of a test ROM.
+struct Case {
+    rom: &'static str, // File name without the extension.
+    golden: &'static str, // Golden image name, some ROMs are run more than once.
+    platform: Platform,
+    selector: u8, // Written to 0x1FF every frame, the test suite reads it to skip its menus.
+    frames: u32, // Frames to run before the screen is checked.
+    keys: &'static [KeyEvent],
+}
+
+fn run(case: Case) {
+    let rom: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("roms").join(format!("{}.ch8", case.rom));
+
+    if !rom.exists() {
+        assert!(std::env::var_os("TIMENDUS_REQUIRED").is_none(), "{} is missing, run tests/fetch-roms.sh to download it", rom.display());
+
+        eprintln!("Skipping {}, {} is missing, run tests/fetch-roms.sh to download it", case.golden, rom.display());
+        return;
+    }
+
+    let mut emulator: Emulator = load(&rom.to_string_lossy(), case.platform).unwrap();
+    emulator.rng = Box::new(FixedRandom::new(vec![0x5A]));
+
+    run_frames(&mut emulator, case.golden, case.frames, case.keys, |emulator| emulator.memory[0x1FF] = case.selector);
+
+    check_golden(&emulator, case.golden);
+}
+
+#[test]
+fn chip8_logo() {
+    run(Case { rom: "1-chip8-logo", golden: "1-chip8-logo", platform: Platform::Vip, selector: 1, frames: 60, keys: &[] });
+}
+
+#[test]
+fn ibm_logo() {
+    run(Case { rom: "2-ibm-logo", golden: "2-ibm-logo", platform: Platform::Vip, selector: 1, frames: 60, keys: &[] });
+}
+
+#[test]
+fn corax_plus() {
+    run(Case { rom: "3-corax+", golden: "3-corax+", platform: Platform::Vip, selector: 1, frames: 120, keys: &[] });
+}
+
+#[test]
+fn flags() {
+    run(Case { rom: "4-flags", golden: "4-flags", platform: Platform::Vip, selector: 1, frames: 120, keys: &[] });
+}
+
+// 1 picks the CHIP-8 quirks from the menu, 2 SUPER-CHIP and 3 XO-CHIP.
+#[test]
+fn quirks_chip8() {
+    run(Case { rom: "5-quirks", golden: "5-quirks-chip8", platform: Platform::Vip, selector: 1, frames: 600, keys: &[] });
+}
+
+#[test]
+fn quirks_schip() {
+    run(Case { rom: "5-quirks", golden: "5-quirks-schip", platform: Platform::Schip11, selector: 2, frames: 600, keys: &[] });
+}
+
+#[test]
+fn quirks_xochip() {
+    run(Case { rom: "5-quirks", golden: "5-quirks-xochip", platform: Platform::XoChip, selector: 3, frames: 600, keys: &[] });
+}
+
+// 1 tests Ex9E with key 5 held, 2 tests ExA1 with it released, 3 tests Fx0A with a press and release.
+#[test]
+fn keypad_down() {
+    let keys: &[KeyEvent] = &[KeyEvent { frame: 30, key: 0x5, down: true }];
+    run(Case { rom: "6-keypad", golden: "6-keypad-down", platform: Platform::Vip, selector: 1, frames: 60, keys });
+}
+
+#[test]
+fn keypad_up() {
+    run(Case { rom: "6-keypad", golden: "6-keypad-up", platform: Platform::Vip, selector: 2, frames: 60, keys: &[] });
+}
+
+#[test]
+fn keypad_getkey() {
+    let keys: &[KeyEvent] = &[
+        KeyEvent { frame: 30, key: 0x5, down: true },
+        KeyEvent { frame: 40, key: 0x5, down: false },
+    ];
+    run(Case { rom: "6-keypad", golden: "6-keypad-getkey", platform: Platform::Vip, selector: 3, frames: 60, keys });
+}
+
+// Holding B beeps, the screen shouldn't change.
+#[test]
+fn beep() {
+    let keys: &[KeyEvent] = &[KeyEvent { frame: 30, key: 0xB, down: true }];
+    run(Case { rom: "7-beep", golden: "7-beep", platform: Platform::Vip, selector: 1, frames: 60, keys });
+}